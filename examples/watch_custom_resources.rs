@@ -0,0 +1,41 @@
+// Derives of serde_derive 0.9 implement traits inside anonymous constants.
+#![allow(non_local_definitions)]
+
+extern crate kubewatch;
+#[macro_use]
+extern crate serde_derive;
+
+use kubewatch::{Events, Resource, WatchEvent};
+
+mod widget {
+    #[derive(Deserialize, Debug)]
+    pub struct Widget {
+        pub metadata: Metadata,
+        pub spec: Spec,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct Metadata {
+        pub name: String,
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct Spec {
+        pub size: u32,
+    }
+}
+
+fn main() {
+    let cluster = kubewatch::Cluster::new("http://localhost:8080").unwrap();
+    let widgets = Resource::api_version("example.com/v1", "widgets").namespace("default");
+    let events = cluster.events_typed::<widget::Widget>(&widgets.path()).unwrap();
+    for event in events {
+        match event {
+            Ok(WatchEvent::Added(widget)) | Ok(WatchEvent::Modified(widget)) => {
+                println!("{} has size {}", widget.metadata.name, widget.spec.size)
+            }
+            Ok(WatchEvent::Deleted(widget)) => println!("{} deleted", widget.metadata.name),
+            other => println!("{:#?}", other),
+        }
+    }
+}
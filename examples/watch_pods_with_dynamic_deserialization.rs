@@ -6,7 +6,7 @@ use kubewatch::Events;
 fn main() {
     let cluster = kubewatch::Cluster::new("http://localhost:8080").unwrap();
     let events = cluster.events::<serde_json::Value>("api/v1/pods").unwrap();
-    for event in events.into_iter() {
+    for event in events {
         println!("{:#?}", event);
     }
 }
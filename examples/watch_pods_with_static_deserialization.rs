@@ -1,19 +1,15 @@
+// Derives of serde_derive 0.9 implement traits inside anonymous constants.
+#![allow(non_local_definitions)]
+
 extern crate kubewatch;
 #[macro_use]
 extern crate serde_derive;
 
-use kubewatch::Events;
+use kubewatch::{Events, WatchEvent};
 
 mod pod {
     #[derive(Deserialize, Debug)]
-    pub struct Event {
-        #[serde(rename = "type")]
-        pub event_type: String,
-        pub object: Object,
-    }
-
-    #[derive(Deserialize, Debug)]
-    pub struct Object {
+    pub struct Pod {
         pub metadata: Metadata,
     }
 
@@ -25,8 +21,13 @@ mod pod {
 
 fn main() {
     let cluster = kubewatch::Cluster::new("http://localhost:8080").unwrap();
-    let events = cluster.events::<pod::Event>("api/v1/pods").unwrap();
-    for event in events.into_iter() {
-        println!("{:#?}", event);
+    let events = cluster.events_typed::<pod::Pod>("api/v1/pods").unwrap();
+    for event in events {
+        match event {
+            Ok(WatchEvent::Added(pod)) => println!("added {}", pod.metadata.name),
+            Ok(WatchEvent::Modified(pod)) => println!("modified {}", pod.metadata.name),
+            Ok(WatchEvent::Deleted(pod)) => println!("deleted {}", pod.metadata.name),
+            other => println!("{:#?}", other),
+        }
     }
-}
\ No newline at end of file
+}
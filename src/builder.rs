@@ -0,0 +1,432 @@
+//! Builder combining multiple `Cluster` configuration options.
+
+use hyper;
+use hyper::client::Client;
+use hyper::error::ParseError;
+use hyper::header::Headers;
+use native_tls::{Certificate, Identity, TlsConnector};
+use pkcs8;
+use socks::Socks5;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use {set_base_path, Cluster, Error, Format, Impersonation, Json, ReconnectPolicy, RetryPolicy,
+     MAX_REDIRECTS, USER_AGENT};
+
+/// Builder of `Cluster`, allowing to combine options which are not available through its
+/// constructors.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let ca = std::fs::read("/etc/kubernetes/pki/ca.crt").unwrap();
+/// let cluster = kubewatch::ClusterBuilder::new()
+///     .host("https://127.0.0.1:6443")
+///     .token("secret")
+///     .ca(&ca)
+///     .read_timeout(Duration::from_secs(300))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ClusterBuilder {
+    host: Option<String>,
+    base_path: Option<String>,
+    token: Option<String>,
+    basic_auth: Option<(String, String)>,
+    ca: Option<Vec<u8>>,
+    client_cert: Option<(Vec<u8>, Vec<u8>)>,
+    connect_timeout: Option<Duration>,
+    keep_alive: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    headers: Vec<(String, String)>,
+    impersonation: Option<Impersonation>,
+    gzip: bool,
+    max_redirects: Option<u32>,
+    retry: RetryPolicy,
+    reconnect: ReconnectPolicy,
+    proxy: Option<String>,
+    socks5: Option<Socks5>,
+    unix_socket: Option<PathBuf>,
+    client: Option<Arc<Client>>,
+    capacity: Option<usize>,
+    namespace: Option<String>,
+    cluster_scoped: Vec<String>,
+    format: Option<Arc<dyn Format>>,
+    danger_accept_invalid_certs: bool,
+}
+
+impl ClusterBuilder {
+    /// Initialize builder with no options set.
+    pub fn new() -> ClusterBuilder {
+        ClusterBuilder::default()
+    }
+
+    /// Set host address and port of the API server (e.g. http://127.0.0.1:8080), mandatory.
+    pub fn host(mut self, host: &str) -> ClusterBuilder {
+        self.host = Some(host.to_owned());
+        self
+    }
+
+    /// Set path under which the API is served, see `Cluster::set_base_path`. By default, the
+    /// path of the host is used.
+    pub fn base_path(mut self, base_path: &str) -> ClusterBuilder {
+        self.base_path = Some(base_path.to_owned());
+        self
+    }
+
+    /// Set bearer token sent in the `Authorization` header of every request.
+    pub fn token(mut self, token: &str) -> ClusterBuilder {
+        self.token = Some(token.to_owned());
+        self
+    }
+
+    /// Set username and password sent in the `Authorization` header of every request, unless a
+    /// bearer token is set as well.
+    pub fn basic_auth(mut self, user: &str, pass: &str) -> ClusterBuilder {
+        self.basic_auth = Some((user.to_owned(), pass.to_owned()));
+        self
+    }
+
+    /// Set PEM encoded CA certificate, server certificate will be validated against it only.
+    pub fn ca(mut self, ca_pem: &[u8]) -> ClusterBuilder {
+        self.ca = Some(ca_pem.to_owned());
+        self
+    }
+
+    /// Set PEM encoded client certificate and private key used to authenticate against the
+    /// server via TLS. The key may be in PKCS #8, PKCS #1 (RSA) or SEC1 (EC) form.
+    pub fn client_cert(mut self, cert_pem: &[u8], key_pem: &[u8]) -> ClusterBuilder {
+        self.client_cert = Some((cert_pem.to_owned(), key_pem.to_owned()));
+        self
+    }
+
+    /// Set timeout for establishing connections, see `Cluster::set_connect_timeout`.
+    pub fn connect_timeout(mut self, timeout: Duration) -> ClusterBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Enable TCP keep-alive probing idle connections every `interval`, see
+    /// `Cluster::set_keep_alive`.
+    pub fn keep_alive(mut self, interval: Duration) -> ClusterBuilder {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Set timeout for reading from the server, see `Cluster::set_read_timeout`.
+    pub fn read_timeout(mut self, timeout: Duration) -> ClusterBuilder {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Set timeout for sending requests to the server.
+    pub fn write_timeout(mut self, timeout: Duration) -> ClusterBuilder {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Set `User-Agent` sent with every request, see `Cluster::set_user_agent`.
+    pub fn user_agent(mut self, user_agent: &str) -> ClusterBuilder {
+        self.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    /// Add header sent with every request, see `Cluster::add_header`.
+    pub fn header(mut self, name: &str, value: &str) -> ClusterBuilder {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Act as another user, see `Cluster::set_impersonation`.
+    pub fn impersonation(mut self, impersonation: Impersonation) -> ClusterBuilder {
+        self.impersonation = Some(impersonation);
+        self
+    }
+
+    /// Ask the server to compress responses with gzip, see `Cluster::set_gzip`.
+    pub fn gzip(mut self, gzip: bool) -> ClusterBuilder {
+        self.gzip = gzip;
+        self
+    }
+
+    /// Set maximal number of followed redirects, see `Cluster::set_max_redirects`.
+    pub fn max_redirects(mut self, max_redirects: u32) -> ClusterBuilder {
+        self.max_redirects = Some(max_redirects);
+        self
+    }
+
+    /// Set policy of retrying transient failures while establishing watches. By default, the
+    /// first failure is returned.
+    pub fn retry(mut self, retry: RetryPolicy) -> ClusterBuilder {
+        self.retry = retry;
+        self
+    }
+
+    /// Set policy of re-establishing watches of `Cluster::events_reconnecting` once they are
+    /// closed. By default, reconnecting continues until the watch is stopped.
+    pub fn reconnect_policy(mut self, reconnect: ReconnectPolicy) -> ClusterBuilder {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Send all requests through HTTP proxy at given address, see `Cluster::with_proxy`.
+    pub fn proxy(mut self, proxy: &str) -> ClusterBuilder {
+        self.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    /// Tunnel all connections through SOCKS5 proxy at given address, see `Cluster::with_socks5`.
+    /// It cannot be combined with an HTTP proxy, which is then not taken from environment
+    /// variables either.
+    pub fn socks5(mut self, proxy: &str, auth: Option<(&str, &str)>) -> ClusterBuilder {
+        self.socks5 = Some(Socks5 {
+            address: proxy.to_owned(),
+            auth: auth.map(|(user, password)| (user.to_owned(), password.to_owned())),
+        });
+        self
+    }
+
+    /// Use given `Client` for all requests, see `Cluster::with_client`.
+    pub fn client(mut self, client: Client) -> ClusterBuilder {
+        self.client = Some(Arc::new(client));
+        self
+    }
+
+    /// Limit number of received events buffered by a watch until they are read. Once the limit
+    /// is reached, the watch is stopped with `Error::ConsumerLagged`. By default, the number of
+    /// buffered events is not limited.
+    pub fn capacity(mut self, capacity: usize) -> ClusterBuilder {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Set namespace watched unless another one is requested, see `Cluster::set_namespace`.
+    pub fn namespace(mut self, namespace: &str) -> ClusterBuilder {
+        self.namespace = Some(namespace.to_owned());
+        self
+    }
+
+    /// Mark resources of given plural `resource` name as cluster-scoped, see
+    /// `Cluster::set_cluster_scoped`. Can be called multiple times.
+    pub fn cluster_scoped(mut self, resource: &str) -> ClusterBuilder {
+        self.cluster_scoped.push(resource.to_owned());
+        self
+    }
+
+    /// Set format of watch streams requested from the server, see `Cluster::set_format`.
+    pub fn format<F>(mut self, format: F) -> ClusterBuilder
+        where F: Format + 'static
+    {
+        self.format = Some(Arc::new(format));
+        self
+    }
+
+    /// Connect to the server over a Unix domain socket at `path` instead of TCP, see
+    /// `Cluster::over_unix_socket`. The host defaults to `http://localhost`, it is sent in the
+    /// `Host` header only.
+    pub fn unix_socket(mut self, path: &Path) -> ClusterBuilder {
+        self.unix_socket = Some(path.to_owned());
+        self
+    }
+
+    /// Disable verification of server certificate and its hostname, so any server is trusted,
+    /// including one impersonating the API server. Meant for local development against
+    /// self-signed servers only, never enable it in production. Disabled by default.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> ClusterBuilder {
+        self.danger_accept_invalid_certs = accept;
+        self
+    }
+
+    /// Build `Cluster` with configured options.
+    pub fn build(self) -> Result<Cluster, Error> {
+        if cfg!(not(unix)) && self.unix_socket.is_some() {
+            let message = "Unix domain sockets are not supported on this platform";
+            return Err(Error::InvalidConfig(message.to_owned()));
+        }
+        let host = match self.host {
+            Some(host) => host,
+            None if self.unix_socket.is_some() => "http://localhost".to_owned(),
+            None => return Err(Error::InvalidUrl(ParseError::EmptyHost)),
+        };
+        let mut host = hyper::Url::parse(&host).map_err(Error::InvalidUrl)?;
+        let base_path = self.base_path.unwrap_or_else(|| host.path().to_owned());
+        set_base_path(&mut host, &base_path);
+        let mut tls = TlsConnector::builder();
+        if let Some(ref ca_pem) = self.ca {
+            let ca = Certificate::from_pem(ca_pem).map_err(|e| Error::TlsError(Box::new(e)))?;
+            tls.add_root_certificate(ca).disable_built_in_roots(true);
+        }
+        if let Some((ref cert_pem, ref key_pem)) = self.client_cert {
+            let identity = Identity::from_pkcs8(cert_pem, &pkcs8::pkcs8(key_pem))
+                .map_err(Error::InvalidClientCertificate)?;
+            tls.identity(identity);
+        }
+        if self.danger_accept_invalid_certs {
+            tls.danger_accept_invalid_certs(true).danger_accept_invalid_hostnames(true);
+        }
+        let tls = tls.build().map_err(|e| Error::TlsError(Box::new(e)))?;
+        if self.proxy.is_some() && self.socks5.is_some() {
+            let message = "HTTP and SOCKS5 proxies cannot be combined";
+            return Err(Error::InvalidConfig(message.to_owned()));
+        }
+        let proxy = match self.proxy {
+            Some(proxy) => Some(proxy),
+            None if self.unix_socket.is_some() || self.socks5.is_some() => None,
+            None => proxy_from_env(&host, |name| env::var(name).ok()),
+        };
+        let proxy = match proxy {
+            Some(proxy) => {
+                let proxy = hyper::Url::parse(&proxy).map_err(Error::InvalidUrl)?;
+                if proxy.scheme() != "http" || proxy.host_str().is_none() {
+                    return Err(Error::InvalidConfig(format!("unsupported proxy {}", proxy)));
+                }
+                Some(proxy)
+            }
+            None => None,
+        };
+        let mut headers = Headers::new();
+        for (name, value) in self.headers {
+            headers.set_raw(name, vec![value.into_bytes()]);
+        }
+        let mut cluster = Cluster {
+            host,
+            token: self.token,
+            basic_auth: self.basic_auth,
+            tls,
+            connect_timeout: self.connect_timeout,
+            keep_alive: self.keep_alive,
+            read_timeout: self.read_timeout,
+            write_timeout: self.write_timeout,
+            user_agent: self.user_agent.unwrap_or_else(|| USER_AGENT.to_owned()),
+            headers,
+            impersonation: self.impersonation,
+            gzip: self.gzip,
+            max_redirects: self.max_redirects.unwrap_or(MAX_REDIRECTS),
+            retry: self.retry,
+            proxy,
+            socks5: self.socks5,
+            unix_socket: self.unix_socket,
+            client: self.client,
+            pool: None,
+            capacity: self.capacity,
+            namespace: self.namespace,
+            cluster_scoped: self.cluster_scoped,
+            format: self.format.unwrap_or_else(|| Arc::new(Json)),
+            reconnect: self.reconnect,
+            reconnect_slots: Arc::default(),
+        };
+        if cluster.client.is_none() {
+            cluster.reset_pool();
+        }
+        Ok(cluster)
+    }
+}
+
+/// Find proxy for `host` in environment variables given by `var`, following conventions of
+/// `curl` and `kubectl`.
+fn proxy_from_env<Var>(host: &hyper::Url, var: Var) -> Option<String>
+    where Var: Fn(&str) -> Option<String>
+{
+    let lookup = |name: &str| var(name).or_else(|| var(&name.to_lowercase()));
+    let no_proxy = lookup("NO_PROXY").unwrap_or_default();
+    let name = host.host_str().unwrap_or_default();
+    let excluded = no_proxy.split(',').map(str::trim).any(|pattern| {
+        let pattern = pattern.trim_start_matches('.');
+        pattern == "*" || name == pattern || name.ends_with(&format!(".{}", pattern))
+    });
+    if excluded {
+        return None;
+    }
+    let proxy = if host.scheme() == "https" {
+        lookup("HTTPS_PROXY")
+    } else {
+        lookup("HTTP_PROXY")
+    };
+    proxy.filter(|proxy| !proxy.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let cluster = ClusterBuilder::new()
+            .host("http://rust-lang.org")
+            .token("secret")
+            .read_timeout(Duration::from_secs(1))
+            .header("X-Auth-Request-User", "watcher")
+            .build()
+            .unwrap();
+        assert_eq!(cluster.token, Some("secret".to_owned()));
+        assert_eq!(cluster.headers.get_raw("x-auth-request-user"),
+                   Some(&[b"watcher".to_vec()][..]));
+        assert_eq!(cluster.read_timeout, Some(Duration::from_secs(1)));
+        assert_eq!(cluster.write_timeout, None);
+    }
+
+    #[test]
+    fn danger_accept_invalid_certs() {
+        assert!(!ClusterBuilder::new().danger_accept_invalid_certs);
+        let builder = ClusterBuilder::new()
+            .host("https://10.0.0.1:6443")
+            .danger_accept_invalid_certs(true);
+        assert!(builder.danger_accept_invalid_certs);
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn build_with_base_path() {
+        let cluster = ClusterBuilder::new()
+            .host("http://127.0.0.1:8001/ignored")
+            .base_path("k8s-api")
+            .build()
+            .unwrap();
+        assert_eq!(cluster.host.as_str(), "http://127.0.0.1:8001/k8s-api/");
+    }
+
+    #[test]
+    fn build_with_invalid_proxy() {
+        let cluster = ClusterBuilder::new()
+            .host("https://10.0.0.1:6443")
+            .proxy("socks5://proxy:1080")
+            .build();
+        assert!(matches!(cluster, Err(Error::InvalidConfig(_))));
+    }
+
+    fn var(name: &str) -> Option<String> {
+        match name {
+            "https_proxy" => Some("http://secure:3128".to_owned()),
+            "HTTP_PROXY" => Some("http://plain:3128".to_owned()),
+            "NO_PROXY" => Some("localhost, .svc.cluster.local".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn proxy_from_env_scheme() {
+        let host = hyper::Url::parse("https://10.0.0.1:6443").unwrap();
+        assert_eq!(proxy_from_env(&host, var), Some("http://secure:3128".to_owned()));
+        let host = hyper::Url::parse("http://10.0.0.1:8080").unwrap();
+        assert_eq!(proxy_from_env(&host, var), Some("http://plain:3128".to_owned()));
+    }
+
+    #[test]
+    fn proxy_from_env_no_proxy() {
+        let host = hyper::Url::parse("http://localhost:8080").unwrap();
+        assert_eq!(proxy_from_env(&host, var), None);
+        let host = hyper::Url::parse("https://kubernetes.default.svc.cluster.local").unwrap();
+        assert_eq!(proxy_from_env(&host, var), None);
+    }
+
+    #[test]
+    fn build_without_host() {
+        let cluster = ClusterBuilder::new().token("secret").build();
+        assert!(matches!(cluster, Err(Error::InvalidUrl(ParseError::EmptyHost))));
+    }
+}
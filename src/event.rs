@@ -0,0 +1,347 @@
+//! Typed representation of frames sent by Kubernetes watch API.
+
+use serde::de::{Deserialize, Deserializer, Error};
+use serde_json::{self, Value};
+
+/// Names of event types known to Kubernetes watch API.
+const EVENT_TYPES: &[&str] = &["ADDED", "MODIFIED", "DELETED", "BOOKMARK", "ERROR"];
+
+/// Single frame of a watch stream, carrying the changed object of type `T`.
+#[derive(Debug, PartialEq)]
+pub enum WatchEvent<T> {
+    /// Object was created.
+    Added(T),
+    /// Object was changed.
+    Modified(T),
+    /// Object was removed.
+    Deleted(T),
+    /// Server marks the progress of the watch up to given resource version, the frame does not
+    /// carry a full object. Sent only if `WatchOptions::allow_watch_bookmarks` is set.
+    Bookmark(String),
+    /// Server failed to continue the watch. Expired resource versions are not reported as this
+    /// event when watching a `Cluster`, but as `Error::Expired`.
+    Error(Status),
+}
+
+impl<T> Deserialize for WatchEvent<T>
+    where T: Deserialize
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let (event_type, object) = match <Value as Deserialize>::deserialize(deserializer)? {
+            Value::Object(mut frame) => {
+                (frame.remove("type"), frame.remove("object").unwrap_or(Value::Null))
+            }
+            _ => return Err(D::Error::custom("watch event is not an object")),
+        };
+        let event_type = match event_type {
+            Some(Value::String(event_type)) => event_type,
+            Some(_) => return Err(D::Error::custom("watch event type is not a string")),
+            None => return Err(D::Error::missing_field("type")),
+        };
+        match event_type.as_str() {
+            "ADDED" => from_value(object).map(WatchEvent::Added),
+            "MODIFIED" => from_value(object).map(WatchEvent::Modified),
+            "DELETED" => from_value(object).map(WatchEvent::Deleted),
+            "BOOKMARK" => {
+                object.pointer("/metadata/resourceVersion")
+                    .and_then(Value::as_str)
+                    .map(|version| WatchEvent::Bookmark(version.to_owned()))
+                    .ok_or_else(|| D::Error::missing_field("resourceVersion"))
+            }
+            "ERROR" => from_value(object).map(WatchEvent::Error),
+            event_type => Err(D::Error::unknown_variant(event_type, EVENT_TYPES)),
+        }
+    }
+}
+
+/// Message of a watch established by `Cluster::watch_with_resync`, carrying objects of type `T`.
+#[derive(Debug, PartialEq)]
+pub enum ResyncEvent<T> {
+    /// All objects currently existing, sent first and after every resync. Objects missing in the
+    /// list were deleted.
+    List(Vec<T>),
+    /// Change of an object since the last list.
+    Event(WatchEvent<T>),
+}
+
+/// Event of type `T` accompanied by the resource version of its object, which is extracted from
+/// the frame regardless of `T`. It can be used to checkpoint progress of a watch.
+///
+/// ```no_run
+/// use kubewatch::{Events, Versioned, WatchEvent};
+///
+/// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+/// let events = cluster
+///     .events::<Versioned<WatchEvent<serde_json::Value>>>("api/v1/pods")
+///     .unwrap();
+/// for event in events {
+///     println!("{:?}", event.unwrap().resource_version);
+/// }
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct Versioned<T> {
+    /// Value of `metadata.resourceVersion` of the object carried by the frame, if any.
+    pub resource_version: Option<String>,
+    /// The event itself.
+    pub event: T,
+}
+
+impl<T> Deserialize for Versioned<T>
+    where T: Deserialize
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let frame = <Value as Deserialize>::deserialize(deserializer)?;
+        let resource_version = frame.pointer("/object/metadata/resourceVersion")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+        Ok(Versioned {
+            resource_version,
+            event: from_value(frame)?,
+        })
+    }
+}
+
+/// Identity of the object carried by a frame, read regardless of the type it is deserialized to.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ObjectHeader {
+    /// API group and version of the object (e.g. `apps/v1`), empty if not sent.
+    pub api_version: String,
+    /// Kind of the object (e.g. `Deployment`), empty if not sent.
+    pub kind: String,
+    /// Name of the object, empty if not sent.
+    pub name: String,
+    /// Namespace of the object, not set for cluster-scoped objects.
+    pub namespace: Option<String>,
+}
+
+/// Event of type `T` accompanied by the header of its object, which is useful when watching
+/// streams of multiple kinds or deserializing events to a dynamic `serde_json::Value`.
+///
+/// ```no_run
+/// use kubewatch::{Events, WatchEvent, WithHeader};
+///
+/// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+/// let events = cluster
+///     .events::<WithHeader<WatchEvent<serde_json::Value>>>("api/v1/pods")
+///     .unwrap();
+/// for event in events {
+///     let header = event.unwrap().header;
+///     println!("{} {} {}", header.api_version, header.kind, header.name);
+/// }
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct WithHeader<T> {
+    /// Header of the object carried by the frame, empty if the frame carries none.
+    pub header: ObjectHeader,
+    /// The event itself.
+    pub event: T,
+}
+
+impl<T> Deserialize for WithHeader<T>
+    where T: Deserialize
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let frame = <Value as Deserialize>::deserialize(deserializer)?;
+        let field = |pointer: &str| {
+            frame.pointer(pointer).and_then(Value::as_str).map(str::to_owned)
+        };
+        let header = ObjectHeader {
+            api_version: field("/object/apiVersion").unwrap_or_default(),
+            kind: field("/object/kind").unwrap_or_default(),
+            name: field("/object/metadata/name").unwrap_or_default(),
+            namespace: field("/object/metadata/namespace"),
+        };
+        Ok(WithHeader {
+            header,
+            event: from_value(frame)?,
+        })
+    }
+}
+
+/// Frame deserialized as `T` if possible, otherwise as `F`, so frames of unexpected kinds do not
+/// fail. Frames fail only if they cannot be deserialized as either type.
+///
+/// ```no_run
+/// #[macro_use]
+/// extern crate serde_derive;
+/// extern crate kubewatch;
+///
+/// use kubewatch::{Events, Fallback, WatchEvent};
+///
+/// #[derive(Deserialize)]
+/// struct Pod {}
+///
+/// fn main() {
+///     let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+///     let events = cluster.events::<Fallback<WatchEvent<Pod>>>("api/v1/pods").unwrap();
+///     for event in events {
+///         match event.unwrap() {
+///             Fallback::Parsed(_) => println!("pod changed"),
+///             Fallback::Other(frame) => println!("unexpected frame {}", frame),
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, PartialEq)]
+pub enum Fallback<T, F = Value> {
+    /// Frame deserialized as the primary type.
+    Parsed(T),
+    /// Frame which could not be deserialized as the primary type.
+    Other(F),
+}
+
+impl<T, F> Deserialize for Fallback<T, F>
+    where T: Deserialize,
+          F: Deserialize
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer
+    {
+        let frame = <Value as Deserialize>::deserialize(deserializer)?;
+        match serde_json::from_value(frame.clone()) {
+            Ok(parsed) => Ok(Fallback::Parsed(parsed)),
+            Err(_) => from_value(frame).map(Fallback::Other),
+        }
+    }
+}
+
+/// Deserialize the object of a frame, reporting failures as an error of the outer deserializer.
+fn from_value<T, E>(object: Value) -> Result<T, E>
+    where T: Deserialize,
+          E: Error
+{
+    serde_json::from_value(object).map_err(E::custom)
+}
+
+/// Status returned by Kubernetes API server when an operation fails.
+#[derive(Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct Status {
+    /// Status of the operation, either `Success` or `Failure`.
+    pub status: String,
+    /// Human-readable description of the status.
+    pub message: String,
+    /// Machine-readable description of the status (e.g. `NotFound` or `Expired`).
+    pub reason: String,
+    /// HTTP status code suggested for the status.
+    pub code: u16,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, PartialEq, Eq, Debug)]
+    struct Pod {
+        name: String,
+    }
+
+    #[test]
+    fn watch_event_added() {
+        let event = r#"{"type": "ADDED", "object": {"name": "nginx"}}"#;
+        let event: WatchEvent<Pod> = serde_json::from_str(event).unwrap();
+        assert_eq!(event, WatchEvent::Added(Pod { name: "nginx".to_owned() }));
+    }
+
+    #[test]
+    fn watch_event_bookmark() {
+        let event = r#"{"type": "BOOKMARK", "object": {"metadata": {"resourceVersion": "42"}}}"#;
+        let event: WatchEvent<Pod> = serde_json::from_str(event).unwrap();
+        assert_eq!(event, WatchEvent::Bookmark("42".to_owned()));
+    }
+
+    #[test]
+    fn watch_event_bookmark_without_version() {
+        let event = r#"{"type": "BOOKMARK", "object": {"metadata": {}}}"#;
+        assert!(serde_json::from_str::<WatchEvent<Pod>>(event).is_err());
+    }
+
+    #[test]
+    fn watch_event_error() {
+        let event = r#"{"type": "ERROR", "object": {"status": "Failure", "reason": "Expired",
+                        "message": "too old resource version", "code": 410}}"#;
+        let event: WatchEvent<Pod> = serde_json::from_str(event).unwrap();
+        assert_eq!(event,
+                   WatchEvent::Error(Status {
+                       status: "Failure".to_owned(),
+                       message: "too old resource version".to_owned(),
+                       reason: "Expired".to_owned(),
+                       code: 410,
+                   }));
+    }
+
+    #[test]
+    fn versioned() {
+        let event = r#"{"type": "ADDED",
+                        "object": {"name": "nginx", "metadata": {"resourceVersion": "42"}}}"#;
+        let event: Versioned<WatchEvent<Pod>> = serde_json::from_str(event).unwrap();
+        assert_eq!(event,
+                   Versioned {
+                       resource_version: Some("42".to_owned()),
+                       event: WatchEvent::Added(Pod { name: "nginx".to_owned() }),
+                   });
+    }
+
+    #[test]
+    fn versioned_without_version() {
+        let event = r#"{"type": "DELETED", "object": {"name": "nginx"}}"#;
+        let event: Versioned<WatchEvent<Pod>> = serde_json::from_str(event).unwrap();
+        assert_eq!(event.resource_version, None);
+    }
+
+    #[test]
+    fn with_header() {
+        let event = r#"{"type": "ADDED", "object": {"apiVersion": "v1", "kind": "Pod",
+                        "metadata": {"name": "nginx", "namespace": "default"}}}"#;
+        let event: WithHeader<WatchEvent<Value>> = serde_json::from_str(event).unwrap();
+        assert_eq!(event.header,
+                   ObjectHeader {
+                       api_version: "v1".to_owned(),
+                       kind: "Pod".to_owned(),
+                       name: "nginx".to_owned(),
+                       namespace: Some("default".to_owned()),
+                   });
+        assert!(matches!(event.event, WatchEvent::Added(_)));
+    }
+
+    #[test]
+    fn with_header_bookmark() {
+        let event = r#"{"type": "BOOKMARK", "object": {"metadata": {"resourceVersion": "42"}}}"#;
+        let event: WithHeader<WatchEvent<Pod>> = serde_json::from_str(event).unwrap();
+        assert_eq!(event.header, ObjectHeader::default());
+        assert_eq!(event.event, WatchEvent::Bookmark("42".to_owned()));
+    }
+
+    #[test]
+    fn fallback() {
+        let event = r#"{"type": "ADDED", "object": {"name": "nginx"}}"#;
+        let event: Fallback<WatchEvent<Pod>> = serde_json::from_str(event).unwrap();
+        assert_eq!(event, Fallback::Parsed(WatchEvent::Added(Pod { name: "nginx".to_owned() })));
+        let event = r#"{"type": "ADDED", "object": {"kind": "Service"}}"#;
+        let event: Fallback<WatchEvent<Pod>> = serde_json::from_str(event).unwrap();
+        assert!(matches!(event, Fallback::Other(Value::Object(_))));
+    }
+
+    #[test]
+    fn fallback_second_type() {
+        let event = r#"{"type": "DELETED", "object": {"kind": "Service"}}"#;
+        let event: Fallback<WatchEvent<Pod>, WatchEvent<Value>> =
+            serde_json::from_str(event).unwrap();
+        assert!(matches!(event, Fallback::Other(WatchEvent::Deleted(_))));
+        let event = r#"{"kind": "Service"}"#;
+        assert!(serde_json::from_str::<Fallback<WatchEvent<Pod>, WatchEvent<Value>>>(event)
+            .is_err());
+    }
+
+    #[test]
+    fn watch_event_unknown_type() {
+        let event = r#"{"type": "RENAMED", "object": {"name": "nginx"}}"#;
+        assert!(serde_json::from_str::<WatchEvent<Pod>>(event).is_err());
+    }
+}
@@ -0,0 +1,508 @@
+//! Formats of watch streams, splitting them into frames and decoding the frames.
+
+use base64;
+use serde::de::Error as DeError;
+use serde_json::{self, Map, Value};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+
+use watch::Frames;
+
+/// Format of watch streams requested from the server. Frames of JSON formats are deserialized
+/// into watched events directly, frames of other formats are decoded to a JSON `Value` first.
+///
+/// ```no_run
+/// let cluster = kubewatch::ClusterBuilder::new()
+///     .host("http://127.0.0.1:8080")
+///     .format(kubewatch::Protobuf)
+///     .build()
+///     .unwrap();
+/// ```
+pub trait Format: fmt::Debug + Send + Sync {
+    /// Media type of the stream, sent in the `Accept` header of watch requests.
+    fn accept(&self) -> &str;
+
+    /// Check whether a watch response of given `content_type` is in this format, others fail
+    /// with `Error::UnexpectedContentType`. Any response is accepted by default.
+    fn accepts(&self, content_type: Option<&str>) -> bool {
+        let _ = content_type;
+        true
+    }
+
+    /// Read the next frame from `reader`, `None` once the stream ended. Frames longer than
+    /// `max_bytes`, if set, must be skipped without buffering them and reported as an `io::Error`
+    /// wrapping `OversizedFrame`, so the stream can continue with the next frame.
+    fn read_frame(&self,
+                  reader: &mut dyn BufRead,
+                  max_bytes: Option<usize>)
+                  -> Option<io::Result<Vec<u8>>>;
+
+    /// Decode a single `frame` to a JSON `Value` of a watch event.
+    fn decode(&self, frame: &[u8]) -> Result<Value, serde_json::Error>;
+
+    /// Check whether frames are JSON text, which events are deserialized from without decoding
+    /// them by `decode`.
+    fn is_json(&self) -> bool {
+        false
+    }
+
+    /// Decode only the `type` of the watch event in `frame`. Formats should override it if the
+    /// type can be read without decoding the whole frame.
+    fn event_type(&self, frame: &[u8]) -> Result<String, serde_json::Error> {
+        match self.decode(frame)? {
+            Value::Object(mut frame) => match frame.remove("type") {
+                Some(Value::String(event_type)) => Ok(event_type),
+                _ => Err(serde_json::Error::missing_field("type")),
+            },
+            _ => Err(serde_json::Error::custom("watch event is not an object")),
+        }
+    }
+}
+
+/// Header of a JSON watch event, other fields are skipped without being decoded.
+#[derive(Deserialize)]
+pub struct Header {
+    #[serde(rename = "type")]
+    pub event_type: String,
+}
+
+/// Stream of JSON objects, the default format.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Format for Json {
+    fn accept(&self) -> &str {
+        "application/json"
+    }
+
+    fn read_frame(&self,
+                  reader: &mut dyn BufRead,
+                  max_bytes: Option<usize>)
+                  -> Option<io::Result<Vec<u8>>> {
+        Frames::new(reader).max_bytes(max_bytes).next()
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<Value, serde_json::Error> {
+        serde_json::from_slice(frame)
+    }
+
+    fn is_json(&self) -> bool {
+        true
+    }
+
+    fn event_type(&self, frame: &[u8]) -> Result<String, serde_json::Error> {
+        serde_json::from_slice::<Header>(frame).map(|header| header.event_type)
+    }
+}
+
+/// Stream of JSON objects carrying only `PartialObjectMetadata` of watched objects, so their
+/// specs and statuses are not transferred. Servers which do not support it send whole objects,
+/// which still deserialize into `PartialObjectMetadata`.
+///
+/// ```no_run
+/// use kubewatch::{Events, PartialObjectMetadata, WatchEvent};
+///
+/// let cluster = kubewatch::ClusterBuilder::new()
+///     .host("http://127.0.0.1:8080")
+///     .format(kubewatch::PartialMetadata)
+///     .build()
+///     .unwrap();
+/// let events = cluster.events_typed::<PartialObjectMetadata>("api/v1/pods").unwrap();
+/// for event in events {
+///     if let Ok(WatchEvent::Added(pod)) = event {
+///         println!("{} {:?}", pod.metadata.name, pod.metadata.labels);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PartialMetadata;
+
+impl Format for PartialMetadata {
+    fn accept(&self) -> &str {
+        "application/json;as=PartialObjectMetadata;g=meta.k8s.io;v=v1,application/json"
+    }
+
+    fn read_frame(&self,
+                  reader: &mut dyn BufRead,
+                  max_bytes: Option<usize>)
+                  -> Option<io::Result<Vec<u8>>> {
+        Json.read_frame(reader, max_bytes)
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<Value, serde_json::Error> {
+        Json.decode(frame)
+    }
+
+    fn is_json(&self) -> bool {
+        true
+    }
+
+    fn event_type(&self, frame: &[u8]) -> Result<String, serde_json::Error> {
+        Json.event_type(frame)
+    }
+}
+
+/// Stream of length-prefixed Kubernetes protobuf messages, which is smaller and faster to parse
+/// than JSON. Protobuf schemas of objects are not known to `kubewatch`, so every event object is
+/// decoded to its `apiVersion`, `kind`, `metadata` (`name`, `namespace`, `uid` and
+/// `resourceVersion`) and the base64 encoded protobuf message in `raw`, which can be decoded by
+/// generated types. `Status` objects of `ERROR` events are decoded completely.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Protobuf;
+
+/// Prefix of Kubernetes protobuf messages.
+const PROTOBUF_MAGIC: &[u8] = b"k8s\0";
+
+/// Longest protobuf frame read unless `WatchOptions::max_event_bytes` is set. Longer lengths
+/// come from a corrupted stream, so they fail the stream instead of being allocated.
+const MAX_PROTOBUF_FRAME_BYTES: usize = 16 * 1024 * 1024;
+
+impl Format for Protobuf {
+    fn accept(&self) -> &str {
+        "application/vnd.kubernetes.protobuf;stream=watch"
+    }
+
+    fn accepts(&self, content_type: Option<&str>) -> bool {
+        content_type.is_some_and(|content_type| {
+            content_type.starts_with("application/vnd.kubernetes.protobuf")
+        })
+    }
+
+    fn read_frame(&self,
+                  reader: &mut dyn BufRead,
+                  max_bytes: Option<usize>)
+                  -> Option<io::Result<Vec<u8>>> {
+        let mut length = [0; 4];
+        match reader.read(&mut length[..1]) {
+            Ok(0) => return None,
+            Ok(_) => (),
+            Err(e) => return Some(Err(e)),
+        }
+        let frame = reader.read_exact(&mut length[1..]).and_then(|()| {
+            let length = u32::from_be_bytes(length) as usize;
+            if max_bytes.is_some_and(|max| length > max) {
+                let skipped = io::copy(&mut reader.take(length as u64), &mut io::sink())?;
+                if skipped < length as u64 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                return Err(oversized_frame());
+            }
+            if length > MAX_PROTOBUF_FRAME_BYTES {
+                let message = format!("protobuf frame of {} bytes is too long", length);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+            // The buffer grows only as the data arrives, not up to the announced length at once.
+            let mut frame = Vec::new();
+            reader.take(length as u64).read_to_end(&mut frame)?;
+            if frame.len() < length {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            Ok(frame)
+        });
+        Some(frame)
+    }
+
+    fn decode(&self, frame: &[u8]) -> Result<Value, serde_json::Error> {
+        // Frames carry raw `metav1.WatchEvent` messages, only their objects are wrapped in the
+        // `runtime.Unknown` envelope.
+        let mut event_type = String::new();
+        let mut object = None;
+        for (field, value) in fields(frame)? {
+            match (field, value) {
+                (1, Wire::Bytes(value)) => event_type = string(value)?,
+                (2, Wire::Bytes(extension)) => {
+                    for (field, value) in fields(extension)? {
+                        if let (1, Wire::Bytes(raw)) = (field, value) {
+                            object = Some(raw);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        let object = object.ok_or_else(|| serde_json::Error::missing_field("object"))?;
+        let (api_version, kind, raw) = unknown(object)?;
+        let mut decoded = Map::new();
+        if kind == "Status" {
+            decode_status(raw, &mut decoded)?;
+        } else {
+            let metadata = metadata(raw)?;
+            if !metadata.is_empty() {
+                decoded.insert("metadata".to_owned(), Value::Object(metadata));
+            }
+            decoded.insert("raw".to_owned(), Value::String(base64::encode(raw)));
+        }
+        decoded.insert("apiVersion".to_owned(), Value::String(api_version));
+        decoded.insert("kind".to_owned(), Value::String(kind));
+        let mut event = Map::new();
+        event.insert("type".to_owned(), Value::String(event_type));
+        event.insert("object".to_owned(), Value::Object(decoded));
+        Ok(Value::Object(event))
+    }
+}
+
+/// Error of a frame exceeding the maximal size given to `Format::read_frame`, wrapped in an
+/// `io::Error`. It is reported as `Error::FrameTooLarge` and the watch continues.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OversizedFrame;
+
+impl fmt::Display for OversizedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "frame exceeds maximal size")
+    }
+}
+
+impl StdError for OversizedFrame {}
+
+/// Build I/O error reporting a skipped `OversizedFrame`.
+pub(crate) fn oversized_frame() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, OversizedFrame)
+}
+
+/// Check whether reading a frame failed because it was too large.
+pub(crate) fn is_oversized_frame(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<OversizedFrame>())
+}
+
+/// Value of a protobuf field, as encoded on the wire. Fixed-size numbers are not used by the
+/// decoded messages, so they are skipped.
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Split protobuf `message` into its fields.
+fn fields<'a>(mut message: &'a [u8]) -> Result<Vec<(u64, Wire<'a>)>, serde_json::Error> {
+    let mut fields = Vec::new();
+    while !message.is_empty() {
+        let key = varint(&mut message)?;
+        let value = match key & 7 {
+            0 => Wire::Varint(varint(&mut message)?),
+            1 | 5 => {
+                let size = if key & 7 == 1 { 8 } else { 4 };
+                take(&mut message, size)?;
+                Wire::Fixed
+            }
+            2 => {
+                let size = varint(&mut message)? as usize;
+                Wire::Bytes(take(&mut message, size)?)
+            }
+            wire => {
+                return Err(DeError::custom(format!("unsupported protobuf wire type {}", wire)))
+            }
+        };
+        fields.push((key >> 3, value));
+    }
+    Ok(fields)
+}
+
+/// Read a base 128 varint from the start of `message`.
+fn varint(message: &mut &[u8]) -> Result<u64, serde_json::Error> {
+    let mut value = 0;
+    for shift in 0..10 {
+        let byte = take(message, 1)?[0];
+        value |= u64::from(byte & 0x7f) << (shift * 7);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DeError::custom("protobuf varint is too long"))
+}
+
+/// Split `size` bytes from the start of `message`.
+fn take<'a>(message: &mut &'a [u8], size: usize) -> Result<&'a [u8], serde_json::Error> {
+    if message.len() < size {
+        return Err(DeError::custom("protobuf message is truncated"));
+    }
+    let (taken, rest) = message.split_at(size);
+    *message = rest;
+    Ok(taken)
+}
+
+fn string(value: &[u8]) -> Result<String, serde_json::Error> {
+    String::from_utf8(value.to_owned()).map_err(|_| DeError::custom("invalid UTF-8 in protobuf"))
+}
+
+/// Unwrap `apiVersion`, `kind` and the raw message from a Kubernetes `runtime.Unknown` envelope.
+fn unknown(message: &[u8]) -> Result<(String, String, &[u8]), serde_json::Error> {
+    if !message.starts_with(PROTOBUF_MAGIC) {
+        return Err(DeError::custom("missing Kubernetes protobuf prefix"));
+    }
+    let (mut api_version, mut kind, mut raw) = (String::new(), String::new(), &[][..]);
+    for (field, value) in fields(&message[PROTOBUF_MAGIC.len()..])? {
+        match (field, value) {
+            (1, Wire::Bytes(type_meta)) => {
+                for (field, value) in fields(type_meta)? {
+                    match (field, value) {
+                        (1, Wire::Bytes(value)) => api_version = string(value)?,
+                        (2, Wire::Bytes(value)) => kind = string(value)?,
+                        _ => (),
+                    }
+                }
+            }
+            (2, Wire::Bytes(value)) => raw = value,
+            _ => (),
+        }
+    }
+    Ok((api_version, kind, raw))
+}
+
+/// Decode identifying fields of `ObjectMeta`, the first field of Kubernetes objects.
+fn metadata(object: &[u8]) -> Result<Map<String, Value>, serde_json::Error> {
+    let mut metadata = Map::new();
+    for (field, value) in fields(object)? {
+        if let (1, Wire::Bytes(meta)) = (field, value) {
+            for (field, value) in fields(meta)? {
+                let name = match field {
+                    1 => "name",
+                    3 => "namespace",
+                    5 => "uid",
+                    6 => "resourceVersion",
+                    _ => continue,
+                };
+                if let Wire::Bytes(value) = value {
+                    metadata.insert(name.to_owned(), Value::String(string(value)?));
+                }
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+/// Decode fields of `Status` message into `decoded`.
+fn decode_status(status: &[u8], decoded: &mut Map<String, Value>) -> Result<(), serde_json::Error> {
+    for (field, value) in fields(status)? {
+        match (field, value) {
+            (2, Wire::Bytes(value)) => {
+                decoded.insert("status".to_owned(), Value::String(string(value)?));
+            }
+            (3, Wire::Bytes(value)) => {
+                decoded.insert("message".to_owned(), Value::String(string(value)?));
+            }
+            (4, Wire::Bytes(value)) => {
+                decoded.insert("reason".to_owned(), Value::String(string(value)?));
+            }
+            (6, Wire::Varint(code)) => {
+                decoded.insert("code".to_owned(), Value::from(code));
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode length-delimited protobuf field.
+    fn field(number: u8, value: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![number << 3 | 2, value.len() as u8];
+        encoded.extend_from_slice(value);
+        encoded
+    }
+
+    /// Wrap `raw` message of given `kind` into a `runtime.Unknown` envelope.
+    fn unknown(kind: &str, raw: &[u8]) -> Vec<u8> {
+        let type_meta = [field(1, b"v1"), field(2, kind.as_bytes())].concat();
+        [PROTOBUF_MAGIC, &field(1, &type_meta), &field(2, raw)].concat()
+    }
+
+    /// Encode `metav1.WatchEvent` frame as sent by the API server, without an envelope.
+    fn event(event_type: &str, object: &[u8]) -> Vec<u8> {
+        [field(1, event_type.as_bytes()), field(2, &field(1, object))].concat()
+    }
+
+    #[test]
+    fn json() {
+        let mut stream = &br#"{"type": "ADDED"} {"type": "DELETED"}"#[..];
+        let frame = Json.read_frame(&mut stream, None).unwrap().unwrap();
+        assert_eq!(Json.decode(&frame).unwrap()["type"], "ADDED");
+        assert!(Json.read_frame(&mut stream, None).is_some());
+        assert!(Json.read_frame(&mut stream, None).is_none());
+    }
+
+    #[test]
+    fn event_type() {
+        let frame = br#"{"type": "MODIFIED", "object": {"spec": [1, {"a": null}]}}"#;
+        assert_eq!(Json.event_type(frame).unwrap(), "MODIFIED");
+        assert!(Json.event_type(br#"{"object": {}}"#).is_err());
+        let frame = event("DELETED", &unknown("Pod", b""));
+        assert_eq!(Protobuf.event_type(&frame).unwrap(), "DELETED");
+    }
+
+    #[test]
+    fn protobuf_read_frame() {
+        let mut stream = &b"\0\0\0\x02ab\0\0\0\x01c\0\0"[..];
+        assert_eq!(Protobuf.read_frame(&mut stream, None).unwrap().unwrap(), b"ab");
+        assert_eq!(Protobuf.read_frame(&mut stream, None).unwrap().unwrap(), b"c");
+        assert!(Protobuf.read_frame(&mut stream, None).unwrap().is_err());
+        assert!(Protobuf.read_frame(&mut stream, None).is_none());
+        let mut stream = &b"\xff\xff\xff\xffabc"[..];
+        let error = Protobuf.read_frame(&mut stream, None).unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn protobuf_accepts() {
+        assert!(Protobuf.accepts(Some("application/vnd.kubernetes.protobuf;stream=watch")));
+        assert!(!Protobuf.accepts(Some("application/json")));
+        assert!(!Protobuf.accepts(None));
+        assert!(Json.accepts(None));
+    }
+
+    #[test]
+    fn read_frame_max_bytes() {
+        let mut stream = &br#"{"a": "long"} {} "#[..];
+        assert!(is_oversized_frame(&Json.read_frame(&mut stream, Some(8)).unwrap().unwrap_err()));
+        assert_eq!(Json.read_frame(&mut stream, Some(8)).unwrap().unwrap(), b"{}");
+        let mut stream = &b"\0\0\0\x03abc\0\0\0\x01d"[..];
+        let frame = Protobuf.read_frame(&mut stream, Some(2)).unwrap();
+        assert!(is_oversized_frame(&frame.unwrap_err()));
+        assert_eq!(Protobuf.read_frame(&mut stream, Some(2)).unwrap().unwrap(), b"d");
+    }
+
+    #[test]
+    fn protobuf_decode() {
+        let meta = [field(1, b"nginx"), field(3, b"default"), field(6, b"42")].concat();
+        let pod = [field(1, &meta), field(2, b"spec")].concat();
+        let frame = event("ADDED", &unknown("Pod", &pod));
+        let event = Protobuf.decode(&frame).unwrap();
+        assert_eq!(event["type"], "ADDED");
+        assert_eq!(event["object"]["kind"], "Pod");
+        assert_eq!(event["object"]["metadata"]["name"], "nginx");
+        assert_eq!(event["object"]["metadata"]["namespace"], "default");
+        assert_eq!(event["object"]["metadata"]["resourceVersion"], "42");
+        assert_eq!(base64::decode(event["object"]["raw"].as_str().unwrap()).unwrap(), pod);
+    }
+
+    #[test]
+    fn protobuf_decode_status() {
+        let status = [field(4, b"Expired"), vec![6 << 3, 0x9a, 0x03]].concat();
+        let frame = event("ERROR", &unknown("Status", &status));
+        let event = Protobuf.decode(&frame).unwrap();
+        assert_eq!(event["object"]["reason"], "Expired");
+        assert_eq!(event["object"]["code"], 410);
+    }
+
+    #[test]
+    fn protobuf_decode_wire() {
+        // ADDED event of a pod named "a", as streamed by the API server.
+        let frame = b"\x0a\x05ADDED\x12\x18\x0a\x16k8s\x00\x0a\x09\x0a\x02v1\x12\x03Pod\
+                      \x12\x05\x0a\x03\x0a\x01a";
+        let event = Protobuf.decode(frame).unwrap();
+        assert_eq!(event["type"], "ADDED");
+        assert_eq!(event["object"]["apiVersion"], "v1");
+        assert_eq!(event["object"]["metadata"]["name"], "a");
+    }
+
+    #[test]
+    fn protobuf_decode_invalid() {
+        assert!(Protobuf.decode(b"{}").is_err());
+        assert!(Protobuf.decode(&[0x0a, 0x05]).is_err());
+        // Objects must be wrapped in the envelope.
+        assert!(Protobuf.decode(&event("ADDED", b"\x0a\x00")).is_err());
+    }
+}
@@ -0,0 +1,472 @@
+//! Streaming decoder of gzip compressed responses (RFC 1952 and RFC 1951).
+//!
+//! The decoder never reads more input than needed to return the data decoded so far, so events
+//! of a watch flushed by the server are returned without waiting for the following ones.
+
+use std::cmp;
+use std::io::{self, Read};
+
+/// Longest Huffman code allowed by DEFLATE.
+const MAX_BITS: usize = 15;
+
+/// Size of the window which back references may point into.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// Base lengths and extra bits of length symbols 257..285.
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43,
+                                51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u32; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4,
+                                 4, 4, 5, 5, 5, 5, 0];
+
+/// Base distances and extra bits of distance symbols 0..29.
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257,
+                                  385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289,
+                                  16385, 24577];
+const DISTANCE_EXTRA: [u32; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9,
+                                   9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Order in which code lengths of the code length alphabet are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2,
+                                        14, 1, 15];
+
+/// CRC-32 (ISO 3309) of every byte value, used to verify the data against the gzip trailer.
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ crc >> 1 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+}
+
+/// Canonical Huffman code, stored as number of codes of each length and symbols ordered by
+/// their codes.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Build code from bit lengths of its symbols, zero length marks an unused symbol.
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(invalid("over-subscribed Huffman code"));
+            }
+        }
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for length in 1..MAX_BITS + 1 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    /// Fixed literal/length and distance codes of block type 1.
+    fn fixed() -> (Huffman, Huffman) {
+        let mut lengths = [0u8; 288];
+        for (symbol, length) in lengths.iter_mut().enumerate() {
+            *length = match symbol {
+                0..=143 => 8,
+                144..=255 => 9,
+                256..=279 => 7,
+                _ => 8,
+            };
+        }
+        let literals = Huffman::new(&lengths).unwrap();
+        let distances = Huffman::new(&[5; 30]).unwrap();
+        (literals, distances)
+    }
+}
+
+/// Position of the decoder within the compressed stream.
+enum State {
+    Header,
+    BlockHeader,
+    Stored(usize),
+    Compressed(Huffman, Huffman),
+    Trailer,
+    Done,
+}
+
+/// Reader decompressing gzip stream read from `inner`.
+pub struct GzipDecoder<R> {
+    inner: R,
+    state: State,
+    last_block: bool,
+    bits: u32,
+    bit_count: u32,
+    window: Vec<u8>,
+    written: usize,
+    /// Inverted CRC-32 of the data written so far.
+    crc: u32,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl<R> GzipDecoder<R>
+    where R: Read
+{
+    pub fn new(inner: R) -> GzipDecoder<R> {
+        GzipDecoder {
+            inner,
+            state: State::Header,
+            last_block: false,
+            bits: 0,
+            bit_count: 0,
+            window: vec![0; WINDOW_SIZE],
+            written: 0,
+            crc: !0,
+            output: Vec::new(),
+            position: 0,
+        }
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0];
+        self.inner.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn skip(&mut self, count: usize) -> io::Result<()> {
+        for _ in 0..count {
+            self.byte()?;
+        }
+        Ok(())
+    }
+
+    /// Read `count` bits, starting with the least significant one.
+    fn bits(&mut self, count: u32) -> io::Result<u32> {
+        while self.bit_count < count {
+            self.bits |= u32::from(self.byte()?) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bits & ((1 << count) - 1);
+        self.bits >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// Drop bits remaining from the current byte.
+    fn align(&mut self) {
+        self.bits = 0;
+        self.bit_count = 0;
+    }
+
+    fn symbol(&mut self, code: &Huffman) -> io::Result<u16> {
+        let (mut value, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &code.counts[1..] {
+            value |= self.bits(1)? as i32;
+            let count = i32::from(count);
+            if value - first < count {
+                return Ok(code.symbols[(index + value - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            value <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+
+    fn emit(&mut self, byte: u8) {
+        self.window[self.written % WINDOW_SIZE] = byte;
+        self.written += 1;
+        self.crc = CRC_TABLE[((self.crc ^ u32::from(byte)) & 0xff) as usize] ^ self.crc >> 8;
+        self.output.push(byte);
+    }
+
+    fn header(&mut self) -> io::Result<()> {
+        let mut header = [0; 10];
+        self.inner.read_exact(&mut header)?;
+        if header[0] != 0x1f || header[1] != 0x8b || header[2] != 8 {
+            return Err(invalid("not a gzip stream"));
+        }
+        let flags = header[3];
+        if flags & 0x04 != 0 {
+            let length = self.byte()? as usize | (self.byte()? as usize) << 8;
+            self.skip(length)?;
+        }
+        for &flag in &[0x08, 0x10] {
+            if flags & flag != 0 {
+                while self.byte()? != 0 {}
+            }
+        }
+        if flags & 0x02 != 0 {
+            self.skip(2)?;
+        }
+        Ok(())
+    }
+
+    /// Verify CRC-32 and size of the data, modulo 2^32, stored in the trailer.
+    fn trailer(&mut self) -> io::Result<()> {
+        self.align();
+        let mut trailer = [0; 8];
+        self.inner.read_exact(&mut trailer)?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc != !self.crc {
+            return Err(invalid("gzip checksum mismatch"));
+        }
+        if size != self.written as u32 {
+            return Err(invalid("gzip size mismatch"));
+        }
+        Ok(())
+    }
+
+    fn block_header(&mut self) -> io::Result<State> {
+        if self.last_block {
+            return Ok(State::Trailer);
+        }
+        self.last_block = self.bits(1)? == 1;
+        match self.bits(2)? {
+            0 => {
+                self.align();
+                let length = self.bits(16)?;
+                if self.bits(16)? != !length & 0xffff {
+                    return Err(invalid("invalid stored block length"));
+                }
+                Ok(State::Stored(length as usize))
+            }
+            1 => {
+                let (literals, distances) = Huffman::fixed();
+                Ok(State::Compressed(literals, distances))
+            }
+            2 => self.dynamic_codes(),
+            _ => Err(invalid("invalid block type")),
+        }
+    }
+
+    fn dynamic_codes(&mut self) -> io::Result<State> {
+        let literal_count = self.bits(5)? as usize + 257;
+        let distance_count = self.bits(5)? as usize + 1;
+        let code_length_count = self.bits(4)? as usize + 4;
+        let mut code_lengths = [0u8; 19];
+        for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[symbol] = self.bits(3)? as u8;
+        }
+        let code = Huffman::new(&code_lengths)?;
+        let mut lengths = Vec::with_capacity(literal_count + distance_count);
+        while lengths.len() < literal_count + distance_count {
+            let (length, repeat) = match self.symbol(&code)? {
+                symbol @ 0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *lengths.last().ok_or_else(|| invalid("no length to repeat"))?;
+                    (previous, 3 + self.bits(2)?)
+                }
+                17 => (0, 3 + self.bits(3)?),
+                _ => (0, 11 + self.bits(7)?),
+            };
+            for _ in 0..repeat {
+                lengths.push(length);
+            }
+        }
+        if lengths.len() > literal_count + distance_count {
+            return Err(invalid("too many code lengths"));
+        }
+        let literals = Huffman::new(&lengths[..literal_count])?;
+        let distances = Huffman::new(&lengths[literal_count..])?;
+        Ok(State::Compressed(literals, distances))
+    }
+
+    /// Decode a single literal or back reference, return `false` at the end of the block.
+    fn compressed(&mut self, literals: &Huffman, distances: &Huffman) -> io::Result<bool> {
+        let symbol = self.symbol(literals)? as usize;
+        if symbol < 256 {
+            self.emit(symbol as u8);
+            return Ok(true);
+        }
+        if symbol == 256 {
+            return Ok(false);
+        }
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err(invalid("invalid length symbol"));
+        }
+        let length = LENGTH_BASE[symbol] as usize + self.bits(LENGTH_EXTRA[symbol])? as usize;
+        let symbol = self.symbol(distances)? as usize;
+        if symbol >= DISTANCE_BASE.len() {
+            return Err(invalid("invalid distance symbol"));
+        }
+        let distance = DISTANCE_BASE[symbol] as usize +
+                       self.bits(DISTANCE_EXTRA[symbol])? as usize;
+        if distance > cmp::min(self.written, WINDOW_SIZE) {
+            return Err(invalid("distance too far back"));
+        }
+        for _ in 0..length {
+            let byte = self.window[(self.written - distance) % WINDOW_SIZE];
+            self.emit(byte);
+        }
+        Ok(true)
+    }
+
+    /// Advance the decoder by a single step, which may or may not produce output.
+    fn step(&mut self) -> io::Result<()> {
+        self.state = match ::std::mem::replace(&mut self.state, State::Done) {
+            State::Header => {
+                self.header()?;
+                State::BlockHeader
+            }
+            State::BlockHeader => self.block_header()?,
+            State::Stored(0) => State::BlockHeader,
+            State::Stored(length) => {
+                let byte = self.byte()?;
+                self.emit(byte);
+                State::Stored(length - 1)
+            }
+            State::Compressed(literals, distances) => {
+                if self.compressed(&literals, &distances)? {
+                    State::Compressed(literals, distances)
+                } else {
+                    State::BlockHeader
+                }
+            }
+            State::Trailer => {
+                self.trailer()?;
+                State::Done
+            }
+            State::Done => State::Done,
+        };
+        Ok(())
+    }
+}
+
+impl<R> Read for GzipDecoder<R>
+    where R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.output.len() {
+            self.output.clear();
+            self.position = 0;
+        }
+        // Keep decoding until the buffer is full, but stop at a block boundary once there is
+        // something to return, the next block may not have been sent yet.
+        loop {
+            let pending = self.output.len() - self.position;
+            let boundary = matches!(self.state,
+                                    State::BlockHeader | State::Trailer | State::Done);
+            if pending >= buf.len() || pending > 0 && boundary {
+                break;
+            }
+            if let State::Done = self.state {
+                break;
+            }
+            self.step()?;
+        }
+        let count = cmp::min(buf.len(), self.output.len() - self.position);
+        buf[..count].copy_from_slice(&self.output[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8]) -> String {
+        let mut decoded = String::new();
+        GzipDecoder::new(data).read_to_string(&mut decoded).unwrap();
+        decoded
+    }
+
+    #[test]
+    fn fixed() {
+        let data = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xab, 0x56,
+                    0xaa, 0x50, 0xb2, 0x52, 0x30, 0xd4, 0x51, 0x50, 0xaa, 0x04, 0xd2, 0x46,
+                    0xb5, 0x00, 0x6b, 0xab, 0x40, 0x8a, 0x10, 0x00, 0x00, 0x00];
+        assert_eq!(decode(&data), r#"{"x": 1, "y": 2}"#);
+    }
+
+    #[test]
+    fn stored() {
+        let data = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x10,
+                    0x00, 0xef, 0xff, 0x7b, 0x22, 0x78, 0x22, 0x3a, 0x20, 0x31, 0x2c, 0x20,
+                    0x22, 0x79, 0x22, 0x3a, 0x20, 0x32, 0x7d, 0x6b, 0xab, 0x40, 0x8a, 0x10,
+                    0x00, 0x00, 0x00];
+        assert_eq!(decode(&data), r#"{"x": 1, "y": 2}"#);
+    }
+
+    #[test]
+    fn dynamic() {
+        let data = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x45, 0x92,
+                    0x49, 0x0e, 0x03, 0x31, 0x08, 0x04, 0xbf, 0x32, 0x9a, 0x73, 0x0e, 0x06,
+                    0xbc, 0xe6, 0x65, 0x19, 0x8d, 0xf2, 0xf7, 0x2c, 0xa2, 0xcb, 0x27, 0xab,
+                    0x5b, 0x08, 0x8a, 0xc6, 0xf7, 0xf9, 0x3a, 0x9f, 0x47, 0x79, 0x1c, 0xe7,
+                    0xf5, 0x7b, 0xdf, 0xf7, 0x5f, 0x5b, 0x6a, 0x4b, 0xed, 0xa9, 0x6b, 0xea,
+                    0x48, 0xbd, 0x52, 0x57, 0xd5, 0xf7, 0x34, 0x5a, 0x1a, 0xde, 0xd2, 0xe8,
+                    0x69, 0x84, 0x2a, 0x86, 0x5a, 0xaa, 0xc7, 0x4c, 0xa3, 0x6b, 0xc8, 0x4a,
+                    0x63, 0x8a, 0xc2, 0x84, 0x69, 0x05, 0x50, 0x48, 0x9d, 0x2a, 0xc1, 0x5a,
+                    0x55, 0x27, 0x0b, 0xf8, 0x34, 0xcd, 0x40, 0x5e, 0x22, 0x32, 0xa0, 0xa1,
+                    0xb6, 0xce, 0x1e, 0x54, 0x09, 0xdc, 0x27, 0xbd, 0x84, 0x1e, 0xce, 0xc4,
+                    0xc5, 0xbe, 0x64, 0x28, 0xfa, 0x0a, 0xbd, 0x8b, 0xbe, 0x56, 0xaa, 0x88,
+                    0x7a, 0xaa, 0x97, 0x8b, 0xbe, 0xb9, 0x26, 0xba, 0xe8, 0xdb, 0x10, 0x97,
+                    0x8b, 0xbe, 0x43, 0xef, 0xa2, 0xef, 0xbb, 0x4a, 0xf4, 0x63, 0xf7, 0x12,
+                    0xfd, 0xd8, 0x13, 0x89, 0x1e, 0xae, 0x10, 0xfd, 0x82, 0x3e, 0x44, 0xbf,
+                    0xd8, 0x31, 0xc8, 0xbe, 0x10, 0x45, 0x10, 0x7e, 0x21, 0xb1, 0x20, 0x7d,
+                    0x23, 0xd8, 0x68, 0x9c, 0x92, 0x0d, 0xa2, 0xe3, 0x71, 0xa6, 0xd0, 0x0a,
+                    0x16, 0x5c, 0x33, 0xe6, 0xbe, 0x39, 0x73, 0xb5, 0x84, 0xb5, 0xef, 0xdf,
+                    0xf8, 0x00, 0x17, 0x79, 0x14, 0x39, 0xe8, 0x02, 0x00, 0x00];
+        let expected: String = (0..40)
+            .map(|i| format!(r#"{{"x": {}, "y": {}}}"#, i, i * i))
+            .collect();
+        assert_eq!(decode(&data), expected);
+    }
+
+    #[test]
+    fn flushed() {
+        // Only the first, flushed, event is available, reading further would block.
+        let data = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xaa, 0x56,
+                    0xaa, 0x50, 0xb2, 0x52, 0x30, 0xd4, 0x51, 0x50, 0xaa, 0x04, 0xd2, 0x46,
+                    0xb5, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff];
+        let mut decoder = GzipDecoder::new(&data[..]);
+        let mut buf = [0; 64];
+        let count = decoder.read(&mut buf).unwrap();
+        assert_eq!(&buf[..count], br#"{"x": 1, "y": 2}"#);
+    }
+
+    #[test]
+    fn corrupted_trailer() {
+        let data = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xab, 0x56,
+                    0xaa, 0x50, 0xb2, 0x52, 0x30, 0xd4, 0x51, 0x50, 0xaa, 0x04, 0xd2, 0x46,
+                    0xb5, 0x00, 0x6b, 0xab, 0x40, 0x8a, 0x10, 0x00, 0x00, 0x00];
+        for &index in &[26, 30] {
+            let mut corrupted = data;
+            corrupted[index] ^= 1;
+            let mut decoded = Vec::new();
+            let result = GzipDecoder::new(&corrupted[..]).read_to_end(&mut decoded);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn invalid_header() {
+        let mut decoded = Vec::new();
+        let result = GzipDecoder::new(&br#"{"x": 1, "y": 2}"#[..]).read_to_end(&mut decoded);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
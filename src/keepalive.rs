@@ -0,0 +1,80 @@
+//! Configuration of TCP keep-alive on connections to the API server.
+
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+
+#[cfg(unix)]
+use libc::{self, c_int, c_void, socklen_t};
+#[cfg(unix)]
+use std::{cmp, mem};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+
+/// Enable keep-alive on `socket`, probing the idle connection every `interval` (rounded to whole
+/// seconds, at least one).
+#[cfg(unix)]
+pub fn enable(socket: &TcpStream, interval: Duration) -> io::Result<()> {
+    let seconds = cmp::min(cmp::max(interval.as_secs(), 1), c_int::MAX as u64) as c_int;
+    set_option(socket, libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    set_option(socket, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, seconds)?;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    set_option(socket, libc::IPPROTO_TCP, libc::TCP_KEEPALIVE, seconds)?;
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios"))]
+    set_option(socket, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, seconds)?;
+    Ok(())
+}
+
+/// Keep-alive cannot be configured on other platforms.
+#[cfg(not(unix))]
+pub fn enable(_: &TcpStream, _: Duration) -> io::Result<()> {
+    Err(io::Error::other("TCP keep-alive is not supported on this platform"))
+}
+
+/// Set integer socket option `name` on given `level` to `value`.
+#[cfg(unix)]
+fn set_option(socket: &TcpStream, level: c_int, name: c_int, value: c_int) -> io::Result<()> {
+    let result = unsafe {
+        libc::setsockopt(socket.as_raw_fd(),
+                         level,
+                         name,
+                         &value as *const c_int as *const c_void,
+                         mem::size_of::<c_int>() as socklen_t)
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn get_option(socket: &TcpStream, level: c_int, name: c_int) -> c_int {
+        let mut value: c_int = 0;
+        let mut length = mem::size_of::<c_int>() as socklen_t;
+        let result = unsafe {
+            libc::getsockopt(socket.as_raw_fd(),
+                             level,
+                             name,
+                             &mut value as *mut c_int as *mut c_void,
+                             &mut length)
+        };
+        assert_eq!(result, 0);
+        value
+    }
+
+    #[test]
+    fn enable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        super::enable(&socket, Duration::from_millis(30500)).unwrap();
+        assert_ne!(get_option(&socket, libc::SOL_SOCKET, libc::SO_KEEPALIVE), 0);
+        #[cfg(target_os = "linux")]
+        assert_eq!(get_option(&socket, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL), 30);
+    }
+}
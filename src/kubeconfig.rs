@@ -0,0 +1,329 @@
+//! Loading of `Cluster` configuration from kubeconfig files, as used by `kubectl`, or from the
+//! service account of a pod.
+
+use base64;
+use serde_yaml;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use {ClusterBuilder, Error};
+
+#[derive(Deserialize, Debug)]
+struct Kubeconfig {
+    #[serde(rename = "current-context", default)]
+    current_context: Option<String>,
+    #[serde(default)]
+    clusters: Vec<NamedCluster>,
+    #[serde(default)]
+    contexts: Vec<NamedContext>,
+    #[serde(default)]
+    users: Vec<NamedUser>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NamedCluster {
+    name: String,
+    cluster: ClusterEntry,
+}
+
+#[derive(Deserialize, Debug)]
+struct ClusterEntry {
+    server: String,
+    #[serde(rename = "certificate-authority")]
+    certificate_authority: Option<String>,
+    #[serde(rename = "certificate-authority-data")]
+    certificate_authority_data: Option<String>,
+    #[serde(rename = "insecure-skip-tls-verify", default)]
+    insecure_skip_tls_verify: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct NamedContext {
+    name: String,
+    context: ContextEntry,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContextEntry {
+    cluster: String,
+    user: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NamedUser {
+    name: String,
+    #[serde(default)]
+    user: UserEntry,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct UserEntry {
+    token: Option<String>,
+    #[serde(rename = "tokenFile")]
+    token_file: Option<String>,
+    #[serde(rename = "client-certificate")]
+    client_certificate: Option<String>,
+    #[serde(rename = "client-certificate-data")]
+    client_certificate_data: Option<String>,
+    #[serde(rename = "client-key")]
+    client_key: Option<String>,
+    #[serde(rename = "client-key-data")]
+    client_key_data: Option<String>,
+}
+
+/// Read kubeconfig from `path` and configure builder according to given `context`, or the
+/// current context if not set.
+pub fn load(path: &Path, context: Option<&str>) -> Result<ClusterBuilder, Error> {
+    let config = read(path)?;
+    let config: Kubeconfig = serde_yaml::from_slice(&config)
+        .map_err(|e| invalid(format!("failed to parse {}: {}", path.display(), e)))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let context = context.map(str::to_owned)
+        .or(config.current_context)
+        .ok_or_else(|| invalid("no context selected".to_owned()))?;
+    let context = config.contexts
+        .iter()
+        .find(|c| c.name == context)
+        .map(|c| &c.context)
+        .ok_or_else(|| invalid(format!("context {} not found", context)))?;
+    let cluster = config.clusters
+        .iter()
+        .find(|c| c.name == context.cluster)
+        .map(|c| &c.cluster)
+        .ok_or_else(|| invalid(format!("cluster {} not found", context.cluster)))?;
+    let user = match context.user {
+        Some(ref user) => {
+            let found = config.users.iter().find(|u| &u.name == user).map(|u| &u.user);
+            Some(found.ok_or_else(|| invalid(format!("user {} not found", user)))?)
+        }
+        None => None,
+    };
+
+    let mut builder = ClusterBuilder::new().host(&cluster.server);
+    if cluster.insecure_skip_tls_verify {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    let ca = data(&cluster.certificate_authority_data, &cluster.certificate_authority, dir)?;
+    if let Some(ca) = ca {
+        builder = builder.ca(&ca);
+    }
+    if let Some(user) = user {
+        let token = match user.token_file {
+            Some(ref file) => Some(String::from_utf8_lossy(&read(&dir.join(file))?).into_owned()),
+            None => user.token.clone(),
+        };
+        if let Some(token) = token {
+            builder = builder.token(token.trim());
+        }
+        let cert = data(&user.client_certificate_data, &user.client_certificate, dir)?;
+        let key = data(&user.client_key_data, &user.client_key, dir)?;
+        match (cert, key) {
+            (Some(cert), Some(key)) => builder = builder.client_cert(&cert, &key),
+            (None, None) => (),
+            _ => return Err(invalid("client certificate and key must be set together".into())),
+        }
+    }
+    Ok(builder)
+}
+
+/// Directory where Kubernetes mounts service account credentials into pods.
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// Configure builder from the service account of the pod this process runs in.
+pub fn in_cluster() -> Result<ClusterBuilder, Error> {
+    service_account(Path::new(SERVICE_ACCOUNT_DIR),
+                    env::var("KUBERNETES_SERVICE_HOST").ok(),
+                    env::var("KUBERNETES_SERVICE_PORT").ok())
+}
+
+/// Configure builder from service account credentials stored in `dir` and API server address.
+fn service_account(dir: &Path,
+                   host: Option<String>,
+                   port: Option<String>)
+                   -> Result<ClusterBuilder, Error> {
+    let host = host.ok_or_else(|| invalid("KUBERNETES_SERVICE_HOST is not set".to_owned()))?;
+    let port = port.ok_or_else(|| invalid("KUBERNETES_SERVICE_PORT is not set".to_owned()))?;
+    let token = read(&dir.join("token"))?;
+    let ca = read(&dir.join("ca.crt"))?;
+    let host = if host.contains(':') {
+        format!("https://[{}]:{}", host, port)
+    } else {
+        format!("https://{}:{}", host, port)
+    };
+    Ok(ClusterBuilder::new()
+        .host(&host)
+        .token(String::from_utf8_lossy(&token).trim())
+        .ca(&ca))
+}
+
+/// Read content given either inline as base64 `data` or as `file` relative to `dir`.
+fn data(data: &Option<String>,
+        file: &Option<String>,
+        dir: &Path)
+        -> Result<Option<Vec<u8>>, Error> {
+    if let Some(ref data) = *data {
+        let decoded = base64::decode(data).map_err(|e| invalid(format!("invalid base64: {}", e)))?;
+        return Ok(Some(decoded));
+    }
+    match *file {
+        Some(ref file) => read(&dir.join(file)).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Read whole file, reporting failures as invalid configuration.
+pub fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    let mut content = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut content))
+        .map_err(|e| invalid(format!("failed to read {}: {}", path.display(), e)))?;
+    Ok(content)
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidConfig(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    const KUBECONFIG: &str = r#"
+apiVersion: v1
+kind: Config
+current-context: dev
+clusters:
+- name: local
+  cluster:
+    server: http://127.0.0.1:8080
+- name: remote
+  cluster:
+    server: https://10.0.0.1:6443
+    insecure-skip-tls-verify: true
+contexts:
+- name: dev
+  context:
+    cluster: local
+    user: developer
+- name: prod
+  context:
+    cluster: remote
+    user: admin
+users:
+- name: developer
+  user:
+    token: c2VjcmV0
+- name: admin
+  user:
+    tokenFile: token
+"#;
+
+    /// Write kubeconfig with a token file to a fresh directory and return path of the config.
+    fn kubeconfig(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("kubewatch-{}-{}", name, ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        File::create(dir.join("config")).unwrap().write_all(KUBECONFIG.as_bytes()).unwrap();
+        File::create(dir.join("token")).unwrap().write_all(b"admin-token\n").unwrap();
+        dir.join("config")
+    }
+
+    #[test]
+    fn load_current_context() {
+        let cluster = load(&kubeconfig("current"), None).unwrap().build().unwrap();
+        assert_eq!(cluster.host.as_str(), "http://127.0.0.1:8080/");
+        assert_eq!(cluster.token, Some("c2VjcmV0".to_owned()));
+    }
+
+    #[test]
+    fn load_named_context() {
+        let cluster = load(&kubeconfig("named"), Some("prod")).unwrap().build().unwrap();
+        assert_eq!(cluster.host.as_str(), "https://10.0.0.1:6443/");
+        assert_eq!(cluster.token, Some("admin-token".to_owned()));
+    }
+
+    #[test]
+    fn load_missing_context() {
+        let builder = load(&kubeconfig("missing"), Some("staging"));
+        assert!(matches!(builder, Err(Error::InvalidConfig(_))));
+    }
+
+    const CLIENT_CERT: &[u8] = include_bytes!("../tests/data/rsa.crt");
+    const CLIENT_KEY: &[u8] = include_bytes!("../tests/data/rsa.key");
+
+    /// Write kubeconfig of a user authenticated by given client certificate fields, together
+    /// with `client.crt` and `client.key` files, and return path of the config.
+    fn client_cert_kubeconfig(name: &str, user: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("kubewatch-{}-{}", name, ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = format!(r#"
+current-context: dev
+clusters:
+- name: remote
+  cluster:
+    server: https://10.0.0.1:6443
+contexts:
+- name: dev
+  context:
+    cluster: remote
+    user: developer
+users:
+- name: developer
+  user:
+{}
+"#,
+                             user);
+        File::create(dir.join("config")).unwrap().write_all(config.as_bytes()).unwrap();
+        File::create(dir.join("client.crt")).unwrap().write_all(CLIENT_CERT).unwrap();
+        File::create(dir.join("client.key")).unwrap().write_all(CLIENT_KEY).unwrap();
+        dir.join("config")
+    }
+
+    #[test]
+    fn load_client_cert_data() {
+        let user = format!("    client-certificate-data: {}\n    client-key-data: {}",
+                           base64::encode(CLIENT_CERT),
+                           base64::encode(CLIENT_KEY));
+        let config = client_cert_kubeconfig("cert-data", &user);
+        assert!(load(&config, None).unwrap().build().is_ok());
+    }
+
+    #[test]
+    fn load_client_cert_files() {
+        let user = "    client-certificate: client.crt\n    client-key: client.key";
+        let config = client_cert_kubeconfig("cert-files", user);
+        assert!(load(&config, None).unwrap().build().is_ok());
+    }
+
+    #[test]
+    fn load_client_cert_without_key() {
+        let config = client_cert_kubeconfig("cert-only", "    client-certificate: client.crt");
+        assert!(matches!(load(&config, None), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let builder = load(Path::new("/does/not/exist"), None);
+        assert!(matches!(builder, Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn service_account_missing_env() {
+        let builder = service_account(Path::new(SERVICE_ACCOUNT_DIR), None, None);
+        assert!(matches!(builder, Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn service_account_missing_files() {
+        let builder = service_account(Path::new("/does/not/exist"),
+                                      Some("10.0.0.1".to_owned()),
+                                      Some("443".to_owned()));
+        assert!(matches!(builder, Err(Error::InvalidConfig(_))));
+    }
+}
@@ -15,31 +15,105 @@
 //! fn main() {
 //!     let cluster = kubewatch::Cluster::new("http://localhost:8080").unwrap();
 //!     let events = cluster.events::<serde_json::Value>("pods").unwrap();
-//!     for event in events.into_iter() {
+//!     for event in events {
 //!         println!("{:#?}", event);
 //!     }
 //! }
 //! ```
 //! Check for more in `examples/`.
 
+// Derives of serde_derive 0.9 implement traits inside anonymous constants.
+#![allow(non_local_definitions)]
+
+extern crate base64;
 extern crate hyper;
+extern crate hyper_native_tls;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+extern crate native_tls;
 extern crate serde_json;
 extern crate serde;
+extern crate serde_yaml;
+#[macro_use]
+extern crate serde_derive;
+extern crate url;
 
 #[cfg(test)]
 #[macro_use]
 extern crate matches;
-#[cfg(test)]
-#[macro_use]
-extern crate serde_derive;
 
-use hyper::client::Client;
+// Diagnostics of watches are logged only with the `log` feature, otherwise the macros expand to
+// nothing but still type check their arguments.
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }
+}
+#[cfg(not(feature = "log"))]
+macro_rules! warn {
+    ($($arg:tt)*) => { if false { let _ = format_args!($($arg)*); } }
+}
+
+mod builder;
+mod event;
+mod format;
+mod gzip;
+mod keepalive;
+mod kubeconfig;
+mod mock;
+mod object;
+mod pages;
+mod pkcs8;
+mod resource;
+mod retry;
+mod socks;
+mod store;
+#[cfg(unix)]
+mod unix;
+mod watch;
+
+pub use builder::ClusterBuilder;
+pub use event::{Fallback, ObjectHeader, ResyncEvent, Status, Versioned, WatchEvent, WithHeader};
+pub use format::{Format, Json, OversizedFrame, PartialMetadata, Protobuf};
+pub use mock::MockCluster;
+pub use object::{KubeObject, ObjectMeta, PartialObjectMetadata};
+pub use pages::Pages;
+pub use resource::Resource;
+pub use retry::{ReconnectPolicy, RetryPolicy};
+pub use store::Store;
+pub use watch::{CancelToken, SharedWatch, Stats, StopHandle, Watch, WatchReader, WatchStatus};
+
+use hyper::client::pool::Pool;
+use hyper::client::{Client, ProxyConfig, RedirectPolicy};
 use hyper::client::response::Response;
-use serde_json::Deserializer;
-use serde::Deserialize;
-use std::io::{self, Read};
-use std::sync::mpsc::{channel, Receiver};
+use hyper::header::{qitem, AcceptEncoding, Authorization, Basic, Bearer, ContentEncoding,
+                    ContentType, Encoding, Headers, Location, UserAgent};
+use hyper::method::Method;
+use hyper::net::{HttpStream, HttpsConnector, NetworkConnector};
+use hyper::status::StatusCode;
+use hyper_native_tls::NativeTlsClient;
+use native_tls::TlsConnector;
+use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, BufReader, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
 use std::thread;
+use std::time::{Duration, Instant};
+use url::form_urlencoded;
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+use gzip::GzipDecoder;
+use retry::ReconnectSlots;
+use watch::{is_timeout, End, Interrupt, IterReader};
 
 /// Covers all errors returned by `kubewatch`.
 #[derive(Debug)]
@@ -47,15 +121,239 @@ pub enum Error {
     /// Failed to parse given URL, check inner `ParseError` for more info.
     InvalidUrl(hyper::error::ParseError),
     /// HTTP request failed (does not apply to non-2xx status), check inner `Error` for more info.
-    HttpRequestFailed(hyper::error::Error),
-    /// Failed while deserializating an event from JSON to Rust.
+    /// The URL the request was sent to is included if known, e.g. to tell apart servers of
+    /// multiple clusters. It is the last location if the request was redirected.
+    HttpRequestFailed(hyper::error::Error, Option<hyper::Url>),
+    /// Failed while deserializating a response body from JSON to Rust.
     DeserializationFailed(serde_json::Error),
+    /// Failed while deserializating an event of a watch from JSON to Rust. The raw event, possibly
+    /// truncated, is included to show what the server sent. The watch continues with the
+    /// following event.
+    InvalidEvent(serde_json::Error, String),
+    /// Failed while serializing a request body from Rust to JSON.
+    SerializationFailed(serde_json::Error),
+    /// Reading of the event stream failed, e.g. because the connection was reset. The stream
+    /// cannot continue, unlike after an `InvalidEvent` error.
+    StreamIo(io::Error),
+    /// Failed to set up TLS or the TLS handshake with the server failed, check inner error for
+    /// more info.
+    TlsError(Box<dyn std::error::Error + Send + Sync>),
+    /// Failed to load client certificate or its private key, check inner `Error` for more info.
+    InvalidClientCertificate(native_tls::Error),
+    /// Server responded with non-2xx status, the response body is included.
+    UnexpectedStatus(StatusCode, String),
+    /// Server responded with non-2xx status and described the failure by a `Status` object, e.g.
+    /// with reason `NotFound` or `Forbidden`.
+    ApiError(Status),
+    /// Connection to the server could not be established within configured timeout, see
+    /// `Cluster::set_connect_timeout`. The server is likely down or unreachable.
+    ConnectTimeout,
+    /// Server did not respond or the watch stream went silent within configured timeout, see
+    /// `Cluster::set_read_timeout`.
+    ReadTimeout,
+    /// Consumer did not keep up with events of a watch with bounded capacity, set by
+    /// `ClusterBuilder::capacity`. Some events were dropped and the watch was stopped, resources
+    /// must be listed again.
+    ConsumerLagged,
+    /// Configuration could not be loaded, the message describes the problem.
+    InvalidConfig(String),
+    /// Requested resource version is too old (HTTP 410 Gone). Resources must be listed again and
+    /// the watch restarted from the version of the list, retrying the same watch will not help.
+    Expired,
+    /// Server is overloaded and asked the client to retry after given time (HTTP 429 Too Many
+    /// Requests). It is waited for automatically when retries are enabled by
+    /// `ClusterBuilder::retry`.
+    RateLimited(Duration),
+    /// Host name of the server could not be resolved, check inner `Error` for more info. Returned
+    /// by `Cluster::check`, other requests fail with `HttpRequestFailed` instead.
+    ResolveFailed(io::Error),
+    /// Server rejected credentials of the client (HTTP 401 Unauthorized), the response body is
+    /// included. Returned by `Cluster::check`.
+    Unauthorized(String),
+    /// Client is not allowed to access the server (HTTP 403 Forbidden), the response body is
+    /// included. Returned by `Cluster::check`.
+    Forbidden(String),
+    /// Event exceeded the maximal size set by `WatchOptions::max_event_bytes`, so it was skipped.
+    /// The watch continues with the following event.
+    FrameTooLarge,
+    /// TCP keep-alive set by `Cluster::set_keep_alive` could not be enabled on a new connection,
+    /// check inner `Error` for more info.
+    KeepAliveFailed(io::Error),
+    /// No event arrived within `WatchOptions::idle_timeout`, so the connection was closed.
+    IdleTimeout,
+    /// Server closed the watch stream right after it was opened without sending any data,
+    /// although it was not asked to by `WatchOptions::timeout_seconds`. The connection likely
+    /// died, e.g. it was cut by a proxy. Watches of `Cluster::events_reconnecting` are
+    /// re-established, others end.
+    EmptyStream,
+    /// Request was redirected more times than allowed by `Cluster::set_max_redirects`, the last
+    /// redirect location is included.
+    TooManyRedirects(hyper::Url),
+    /// Connecting to the SOCKS5 proxy set by `Cluster::with_socks5` failed, or the proxy rejected
+    /// the client, check inner `Error` for more info. Failures of the proxy to reach the server
+    /// are reported as `HttpRequestFailed`.
+    ProxyFailed(io::Error),
+    /// Worker thread of a watch could not be spawned, check inner `Error` for more info.
+    SpawnFailed(io::Error),
+    /// Name of resources is not a valid API path (e.g. `api/v1/pods`), the name is included.
+    InvalidResource(String),
+    /// Server responded to a watch with a content type other than the format set by
+    /// `Cluster::set_format`, e.g. JSON to a protobuf watch. The content type is included.
+    UnexpectedContentType(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidUrl(ref e) => write!(f, "invalid URL: {}", e),
+            Error::HttpRequestFailed(ref e, Some(ref url)) => {
+                write!(f, "HTTP request to {} failed: {}", url, e)
+            }
+            Error::HttpRequestFailed(ref e, None) => write!(f, "HTTP request failed: {}", e),
+            Error::DeserializationFailed(ref e) => write!(f, "failed to deserialize body: {}", e),
+            Error::InvalidEvent(ref e, ref frame) => {
+                write!(f, "failed to deserialize event {:?}: {}", frame, e)
+            }
+            Error::SerializationFailed(ref e) => write!(f, "failed to serialize body: {}", e),
+            Error::StreamIo(ref e) => write!(f, "failed to read event stream: {}", e),
+            Error::TlsError(ref e) => write!(f, "TLS error: {}", e),
+            Error::InvalidClientCertificate(ref e) => {
+                write!(f, "invalid client certificate: {}", e)
+            }
+            Error::UnexpectedStatus(status, ref body) => {
+                write!(f, "unexpected status {}: {}", status, body)
+            }
+            Error::ApiError(ref status) => {
+                write!(f, "API error {} {}: {}", status.code, status.reason, status.message)
+            }
+            Error::ConnectTimeout => write!(f, "failed to connect to server in time"),
+            Error::ReadTimeout => write!(f, "server did not respond in time"),
+            Error::ConsumerLagged => write!(f, "consumer did not keep up with events"),
+            Error::InvalidConfig(ref message) => write!(f, "invalid configuration: {}", message),
+            Error::Expired => write!(f, "resource version is too old"),
+            Error::RateLimited(after) => {
+                write!(f, "rate limited, retry after {} seconds", after.as_secs())
+            }
+            Error::ResolveFailed(ref e) => write!(f, "failed to resolve server host: {}", e),
+            Error::Unauthorized(ref body) => write!(f, "unauthorized: {}", body),
+            Error::Forbidden(ref body) => write!(f, "forbidden: {}", body),
+            Error::FrameTooLarge => write!(f, "event exceeds maximal size"),
+            Error::KeepAliveFailed(ref e) => write!(f, "failed to enable TCP keep-alive: {}", e),
+            Error::IdleTimeout => write!(f, "no event arrived in time"),
+            Error::EmptyStream => write!(f, "watch stream closed before sending anything"),
+            Error::TooManyRedirects(ref url) => write!(f, "too many redirects, last to {}", url),
+            Error::ProxyFailed(ref e) => write!(f, "SOCKS5 proxy failed: {}", e),
+            Error::SpawnFailed(ref e) => write!(f, "failed to spawn worker thread: {}", e),
+            Error::InvalidResource(ref name) => write!(f, "invalid resource name {:?}", name),
+            Error::UnexpectedContentType(ref content_type) => {
+                write!(f, "unexpected content type {:?} of watch stream", content_type)
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::InvalidUrl(ref e) => Some(e),
+            Error::HttpRequestFailed(ref e, _) => Some(e),
+            Error::DeserializationFailed(ref e) |
+            Error::SerializationFailed(ref e) |
+            Error::InvalidEvent(ref e, _) => Some(e),
+            Error::StreamIo(ref e) |
+            Error::ResolveFailed(ref e) |
+            Error::KeepAliveFailed(ref e) |
+            Error::ProxyFailed(ref e) |
+            Error::SpawnFailed(ref e) => Some(e),
+            Error::TlsError(ref e) => Some(&**e),
+            Error::InvalidClientCertificate(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<hyper::error::ParseError> for Error {
+    fn from(error: hyper::error::ParseError) -> Error {
+        Error::InvalidUrl(error)
+    }
+}
+
+impl From<hyper::error::Error> for Error {
+    fn from(error: hyper::error::Error) -> Error {
+        Error::HttpRequestFailed(error, None)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::DeserializationFailed(error)
+    }
 }
 
+/// How often `Cluster::watch_with_resync` checks whether it was stopped while waiting for events.
+const RESYNC_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Maximal number of followed redirects unless overridden by `Cluster::set_max_redirects`, the
+/// same as of `kubectl`.
+const MAX_REDIRECTS: u32 = 10;
+
+/// `User-Agent` sent to the server unless overridden by `Cluster::set_user_agent`.
+const USER_AGENT: &str = concat!("kubewatch/", env!("CARGO_PKG_VERSION"));
+
+/// Built-in resources which are not namespaced, the default namespace is never applied to them.
+const CLUSTER_SCOPED: &[&str] = &["apiservices",
+                                  "certificatesigningrequests",
+                                  "clusterrolebindings",
+                                  "clusterroles",
+                                  "componentstatuses",
+                                  "csidrivers",
+                                  "csinodes",
+                                  "customresourcedefinitions",
+                                  "ingressclasses",
+                                  "mutatingwebhookconfigurations",
+                                  "namespaces",
+                                  "nodes",
+                                  "persistentvolumes",
+                                  "priorityclasses",
+                                  "runtimeclasses",
+                                  "storageclasses",
+                                  "validatingwebhookconfigurations",
+                                  "volumeattachments"];
+
 /// Represents connection to Kubernetes API server.
-#[derive(Debug)]
+///
+/// Cloning a `Cluster` is cheap and keeps all its configuration, including credentials and TLS
+/// settings, so a cluster loaded once (e.g. from kubeconfig) can be cloned for every namespace.
+/// Clones share the pool of kept-alive connections used by lists and other requests, and the
+/// limit of `ReconnectPolicy::max_concurrent`. Every watch opens its own connection regardless.
+/// Changing timeouts or keep-alive of a clone gives it a new pool, not shared with the others.
+#[derive(Debug, Clone)]
 pub struct Cluster {
     host: hyper::Url,
+    token: Option<String>,
+    basic_auth: Option<(String, String)>,
+    tls: TlsConnector,
+    connect_timeout: Option<Duration>,
+    keep_alive: Option<Duration>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    user_agent: String,
+    headers: Headers,
+    impersonation: Option<Impersonation>,
+    gzip: bool,
+    max_redirects: u32,
+    retry: RetryPolicy,
+    proxy: Option<hyper::Url>,
+    socks5: Option<socks::Socks5>,
+    unix_socket: Option<PathBuf>,
+    client: Option<Arc<Client>>,
+    pool: Option<Arc<Client>>,
+    capacity: Option<usize>,
+    namespace: Option<String>,
+    cluster_scoped: Vec<String>,
+    format: Arc<dyn Format>,
+    reconnect: ReconnectPolicy,
+    reconnect_slots: Arc<ReconnectSlots>,
 }
 
 impl Cluster {
@@ -65,102 +363,2911 @@ impl Cluster {
     /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
     /// ```
     pub fn new(host: &str) -> Result<Cluster, Error> {
-        let url = try!(hyper::Url::parse(host).map_err(Error::InvalidUrl));
-        Ok(Cluster { host: url })
+        ClusterBuilder::new().host(host).build()
     }
 
-    /// Run HTTP GET request on given path (will be joined to `Cluster` URL).
-    fn get(&self, path: &str) -> Result<Response, Error> {
-        let url = try!(self.host.join(path).map_err(Error::InvalidUrl));
-        Client::new().get(url).send().map_err(Error::HttpRequestFailed)
+    /// Initialize `Cluster` with host address and a PEM encoded CA certificate. Server
+    /// certificate of `https://` hosts will be validated against the given CA only.
+    ///
+    /// ```no_run
+    /// let ca = std::fs::read("/etc/kubernetes/pki/ca.crt").unwrap();
+    /// let cluster = kubewatch::Cluster::with_ca("https://127.0.0.1:6443", &ca).unwrap();
+    /// ```
+    pub fn with_ca(host: &str, ca_pem: &[u8]) -> Result<Cluster, Error> {
+        ClusterBuilder::new().host(host).ca(ca_pem).build()
     }
-}
 
-/// This trait is used to deserialize input stream and return respective Rust structs.
-pub trait Events {
-    /// Read monitor of events with given `name` and return them as given `Event` structure.
-    fn events<Event>(&self, name: &str) -> Result<Receiver<Result<Event, Error>>, Error>
-        where Event: Deserialize + Send + 'static;
+    /// Initialize `Cluster` with host address and a PEM encoded client certificate and PKCS #8
+    /// private key, which will be used to authenticate against the server via TLS.
+    ///
+    /// ```no_run
+    /// let cert = std::fs::read("client.crt").unwrap();
+    /// let key = std::fs::read("client.key").unwrap();
+    /// let cluster =
+    ///     kubewatch::Cluster::with_client_cert("https://127.0.0.1:6443", &cert, &key).unwrap();
+    /// ```
+    pub fn with_client_cert(host: &str, cert_pem: &[u8], key_pem: &[u8]) -> Result<Cluster, Error> {
+        ClusterBuilder::new().host(host).client_cert(cert_pem, key_pem).build()
+    }
 
-    /// Helper which reads a byte iterator, deserializes it and return respective structures.
-    fn generator<Event, Iter>(&self, iter: Iter) -> Receiver<Result<Event, Error>>
-        where Event: Deserialize + Send + 'static,
-              Iter: Iterator<Item = io::Result<u8>> + Send + 'static
+    /// Initialize `Cluster` with host address and a bearer token which will be sent in the
+    /// `Authorization` header of every request.
+    ///
+    /// ```
+    /// let cluster = kubewatch::Cluster::with_token("http://127.0.0.1:8080", "secret").unwrap();
+    /// ```
+    pub fn with_token(host: &str, token: &str) -> Result<Cluster, Error> {
+        ClusterBuilder::new().host(host).token(token).build()
+    }
+
+    /// Initialize `Cluster` with host address and a username and password which will be sent in
+    /// the `Authorization` header of every request (HTTP Basic authentication). A bearer token
+    /// set later takes precedence over them.
+    ///
+    /// ```
+    /// let host = "http://127.0.0.1:8080";
+    /// let cluster = kubewatch::Cluster::with_basic_auth(host, "admin", "secret").unwrap();
+    /// ```
+    pub fn with_basic_auth(host: &str, user: &str, pass: &str) -> Result<Cluster, Error> {
+        ClusterBuilder::new().host(host).basic_auth(user, pass).build()
+    }
+
+    /// Initialize `Cluster` with host address and address of an HTTP proxy all requests are sent
+    /// through, `https://` hosts are tunneled via `CONNECT`. Without it, the proxy is taken from
+    /// `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment variables.
+    ///
+    /// ```
+    /// let cluster =
+    ///     kubewatch::Cluster::with_proxy("https://10.0.0.1:6443", "http://proxy:3128").unwrap();
+    /// ```
+    pub fn with_proxy(host: &str, proxy: &str) -> Result<Cluster, Error> {
+        ClusterBuilder::new().host(host).proxy(proxy).build()
+    }
+
+    /// Initialize `Cluster` with host address and address (`host:port`) of a SOCKS5 proxy all
+    /// connections are tunneled through, authenticating by username and password if `auth` is
+    /// given. Failures of the proxy itself are reported as `Error::ProxyFailed`.
+    ///
+    /// ```
+    /// let cluster = kubewatch::Cluster::with_socks5("https://10.0.0.1:6443",
+    ///                                               "127.0.0.1:1080",
+    ///                                               Some(("user", "secret")))
+    ///     .unwrap();
+    /// ```
+    pub fn with_socks5(host: &str,
+                       proxy: &str,
+                       auth: Option<(&str, &str)>)
+                       -> Result<Cluster, Error> {
+        ClusterBuilder::new().host(host).socks5(proxy, auth).build()
+    }
+
+    /// Initialize `Cluster` connecting to the API server over a Unix domain socket at `path`,
+    /// e.g. one exposed by a sidecar. Requests are sent as plain HTTP, TLS and proxy options do
+    /// not apply. Supported on Unix platforms only.
+    ///
+    /// ```no_run
+    /// let path = std::path::Path::new("/var/run/kube-api.sock");
+    /// let cluster = kubewatch::Cluster::over_unix_socket(path).unwrap();
+    /// ```
+    pub fn over_unix_socket(path: &Path) -> Result<Cluster, Error> {
+        ClusterBuilder::new().unix_socket(path).build()
+    }
+
+    /// Initialize `Cluster` with host address and a pre-built `Client`, which is used for all
+    /// requests, e.g. to share its connection pool among multiple clusters. TLS, proxy and
+    /// timeout options of the `Cluster` do not apply to such client. Stopped watches close their
+    /// connections only after the next event is received, as they cannot be interrupted.
+    ///
+    /// ```
+    /// let client = hyper::client::Client::new();
+    /// let cluster = kubewatch::Cluster::with_client("http://127.0.0.1:8080", client).unwrap();
+    /// ```
+    pub fn with_client(host: &str, client: Client) -> Result<Cluster, Error> {
+        ClusterBuilder::new().host(host).client(client).build()
+    }
+
+    /// Initialize `Cluster` from kubeconfig file at `path`, using given `context` or the current
+    /// context of the file if not set. Server address, CA and credentials of the user (token or
+    /// client certificate and key) are taken from the context.
+    ///
+    /// ```no_run
+    /// let path = std::path::Path::new("/home/user/.kube/config");
+    /// let cluster = kubewatch::Cluster::from_kubeconfig(path, None).unwrap();
+    /// ```
+    pub fn from_kubeconfig(path: &Path, context: Option<&str>) -> Result<Cluster, Error> {
+        kubeconfig::load(path, context)?.build()
+    }
+
+    /// Initialize `Cluster` from within a pod, using its service account token and CA, and the
+    /// API server address given by `KUBERNETES_SERVICE_HOST` and `KUBERNETES_SERVICE_PORT`
+    /// environment variables.
+    ///
+    /// ```no_run
+    /// let cluster = kubewatch::Cluster::in_cluster().unwrap();
+    /// ```
+    pub fn in_cluster() -> Result<Cluster, Error> {
+        kubeconfig::in_cluster()?.build()
+    }
+
+    /// Set path under which the API is served, e.g. `/k8s-api/` behind a reverse proxy. It is
+    /// prepended to paths of all requested resources, replacing the path given with the host.
+    pub fn set_base_path(&mut self, base_path: &str) {
+        set_base_path(&mut self.host, base_path);
+    }
+
+    /// Set bearer token used to authenticate all following requests.
+    pub fn set_token(&mut self, token: &str) {
+        self.token = Some(token.to_owned());
+    }
+
+    /// Set username and password used to authenticate all following requests, unless a bearer
+    /// token is set.
+    pub fn set_basic_auth(&mut self, user: &str, pass: &str) {
+        self.basic_auth = Some((user.to_owned(), pass.to_owned()));
+    }
+
+    /// Set timeout for establishing connections to the server, which fail with
+    /// `Error::ConnectTimeout` once it expires.
+    pub fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.connect_timeout = Some(timeout);
+        self.reset_pool();
+    }
+
+    /// Enable TCP keep-alive on new connections, probing idle connections every `interval`
+    /// (rounded to whole seconds), so watches are not dropped by load balancers or NAT after a
+    /// period without events. Connections fail with `Error::KeepAliveFailed` if it cannot be
+    /// enabled. Supported on Unix platforms only.
+    pub fn set_keep_alive(&mut self, interval: Duration) {
+        self.keep_alive = Some(interval);
+        self.reset_pool();
+    }
+
+    /// Set timeout for reading from the server. Note that it applies also to the whole watch
+    /// stream, so the watch will fail if no event is received within the timeout.
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.read_timeout = Some(timeout);
+        self.reset_pool();
+    }
+
+    /// Set timeout for sending requests to the server.
+    pub fn set_write_timeout(&mut self, timeout: Duration) {
+        self.write_timeout = Some(timeout);
+        self.reset_pool();
+    }
+
+    /// Set `User-Agent` sent with every request, so the client can be identified in API server
+    /// logs. Defaults to `kubewatch/<version>`.
+    pub fn set_user_agent(&mut self, user_agent: &str) {
+        self.user_agent = user_agent.to_owned();
+    }
+
+    /// Act as another user for all following requests, e.g. to check what the user can watch.
+    /// The server must allow the client to impersonate the user, its groups and extra fields.
+    pub fn set_impersonation(&mut self, impersonation: Impersonation) {
+        self.impersonation = Some(impersonation);
+    }
+
+    /// Ask the server to compress responses with gzip, they are decompressed transparently. This
+    /// reduces bandwidth used by busy watches at the cost of some CPU time.
+    pub fn set_gzip(&mut self, gzip: bool) {
+        self.gzip = gzip;
+    }
+
+    /// Set maximal number of redirects followed by a single request, `10` by default. Requests
+    /// redirected more times fail with `Error::TooManyRedirects`, `0` disables following
+    /// redirects. Credentials are sent only to redirects of the same origin (scheme, host and
+    /// port).
+    pub fn set_max_redirects(&mut self, max_redirects: u32) {
+        self.max_redirects = max_redirects;
+    }
+
+    /// Set format of watch streams requested from the server, `Json` by default. Lists are
+    /// always requested as JSON.
+    pub fn set_format<F>(&mut self, format: F)
+        where F: Format + 'static
     {
-        let (tx, rx) = channel();
-        let stream = Deserializer::from_iter(iter).into_iter::<Event>();
-        thread::spawn(move || for event in stream {
-            if let Err(_) = tx.send(event.map_err(Error::DeserializationFailed)) {
-                break;
-            }
-        });
-        rx
+        self.format = Arc::new(format);
     }
-}
 
-/// Read event monitor from Kubernetes API server.
-impl Events for Cluster {
-    fn events<Event>(&self, name: &str) -> Result<Receiver<Result<Event, Error>>, Error>
+    /// Set namespace used whenever `WatchOptions::namespace` is not given, by watches as well as
+    /// lists, reads and creations of objects. It is not used for watches with
+    /// `WatchOptions::all_namespaces` set, for cluster-scoped resources (see
+    /// `set_cluster_scoped`) and for names which already include a namespace (e.g.
+    /// `api/v1/namespaces/default/pods`).
+    pub fn set_namespace(&mut self, namespace: &str) {
+        self.namespace = Some(namespace.to_owned());
+    }
+
+    /// Mark resources of given plural `resource` name (e.g. `widgets`) as cluster-scoped, so the
+    /// default namespace is not applied to them. Built-in cluster-scoped resources such as
+    /// `nodes` or `namespaces` are recognized without it.
+    ///
+    /// ```
+    /// let mut cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// cluster.set_namespace("default");
+    /// cluster.set_cluster_scoped("widgets");
+    /// ```
+    pub fn set_cluster_scoped(&mut self, resource: &str) {
+        self.cluster_scoped.push(resource.to_owned());
+    }
+
+    /// Clone the cluster with a different namespace watched by default, see `set_namespace`. The
+    /// clone shares connections of lists and other requests with the original.
+    ///
+    /// ```
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let namespaces: Vec<_> = ["default", "kube-system"]
+    ///     .iter()
+    ///     .map(|namespace| cluster.for_namespace(namespace))
+    ///     .collect();
+    /// ```
+    pub fn for_namespace(&self, namespace: &str) -> Cluster {
+        let mut cluster = self.clone();
+        cluster.set_namespace(namespace);
+        cluster
+    }
+
+    /// Add header sent with every request, e.g. one required by an authenticating proxy. It
+    /// replaces any header of the same name set by `kubewatch`, including `User-Agent`.
+    ///
+    /// ```
+    /// let mut cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// cluster.add_header("X-Auth-Request-User", "watcher");
+    /// ```
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.headers.set_raw(name.to_owned(), vec![value.as_bytes().to_vec()]);
+    }
+
+    /// Read monitor of events like `Events::events_with_options`, returning also status and
+    /// headers of the server response, which is useful for debugging. Establishing of this and
+    /// all other watches is retried according to `ClusterBuilder::retry`.
+    ///
+    /// ```no_run
+    /// use kubewatch::WatchOptions;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let (response, events) = cluster
+    ///     .events_with_response::<serde_json::Value>("api/v1/pods", &WatchOptions::default())
+    ///     .unwrap();
+    /// println!("{} {}", response.status, response.headers);
+    /// ```
+    pub fn events_with_response<Event>(&self,
+                                       name: &str,
+                                       options: &WatchOptions)
+                                       -> Result<(ResponseInfo, Watch<Event>), Error>
         where Event: Deserialize + Send + 'static
     {
-        let path = format!("{}?watch=true", name);
-        let bytes = try!(self.get(&path)).bytes();
-        Ok(self.generator(bytes))
+        validate_name(name)?;
+        let interrupt = options.interrupt()?;
+        let options = self.with_default_namespace(name, options);
+        let path = options.path(name);
+        let response = self.retry.run(|| self.get_interruptible(&path, &interrupt))?;
+        let info = ResponseInfo {
+            status: response.status,
+            headers: response.headers.clone(),
+        };
+        let reader = BufReader::new(body(response));
+        let format = self.format.clone();
+        let watch = watch::spawn(name, reader, interrupt, options, self.capacity, format)?;
+        Ok((info, watch))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Watch resources with given `name` and pass every event to `callback`, which runs directly
+    /// in the worker thread as soon as the event is parsed. Unlike `Events::events`, events are
+    /// not buffered in a channel, so a slow callback slows down reading of the stream. The
+    /// returned handle stops the watch.
+    ///
+    /// ```no_run
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let watch = cluster
+    ///     .events_with("api/v1/pods", |event: Result<serde_json::Value, kubewatch::Error>| {
+    ///         println!("{:?}", event);
+    ///     })
+    ///     .unwrap();
+    /// std::thread::sleep(std::time::Duration::from_secs(60));
+    /// watch.stop();
+    /// ```
+    pub fn events_with<Event, Callback>(&self,
+                                        name: &str,
+                                        callback: Callback)
+                                        -> Result<StopHandle, Error>
+        where Event: Deserialize + 'static,
+              Callback: FnMut(Result<Event, Error>) + Send + 'static
+    {
+        validate_name(name)?;
+        let interrupt = Interrupt::default();
+        let options = self.with_default_namespace(name, &WatchOptions::default());
+        let path = options.path(name);
+        let response = self.retry.run(|| self.get_interruptible(&path, &interrupt))?;
+        let reader = BufReader::new(body(response));
+        let format = self.format.clone();
+        watch::spawn_callback(name, reader, interrupt, options, format, callback)
+    }
 
-    #[test]
-    fn cluster() {
-        let cluster = Cluster::new("http://rust-lang.org");
-        assert!(cluster.is_ok());
+    /// Open the watch stream of resources with given `name` without deserializing it, e.g. to log
+    /// it or to feed it to another parser. The stream is decompressed if needed, dropping it
+    /// closes the connection.
+    ///
+    /// ```no_run
+    /// use kubewatch::WatchOptions;
+    /// use std::io::Read;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let mut stream = cluster.events_raw("api/v1/pods", &WatchOptions::default()).unwrap();
+    /// let mut chunk = [0; 4096];
+    /// let read = stream.read(&mut chunk).unwrap();
+    /// println!("{}", String::from_utf8_lossy(&chunk[..read]));
+    /// ```
+    pub fn events_raw(&self,
+                      name: &str,
+                      options: &WatchOptions)
+                      -> Result<Box<dyn Read + Send>, Error> {
+        validate_name(name)?;
+        let interrupt = options.interrupt()?;
+        let path = self.with_default_namespace(name, options).path(name);
+        let response = self.retry.run(|| self.get_interruptible(&path, &interrupt))?;
+        Ok(body(response))
     }
 
-    #[test]
-    fn cluster_invalid_url() {
-        let cluster = Cluster::new("123.456.789.000");
-        assert!(matches!(cluster, Err(Error::InvalidUrl(_))));
+    /// Watch resources with given `name`, reading only the `type` of every event (e.g. `ADDED`)
+    /// instead of deserializing its object. It is much cheaper when only rates of events are
+    /// monitored, the events are counted by `Watch::stats` too.
+    ///
+    /// ```no_run
+    /// use kubewatch::WatchOptions;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let types = cluster.event_types("api/v1/pods", &WatchOptions::default()).unwrap();
+    /// for event_type in &types {
+    ///     println!("{:?}, {} events so far", event_type, types.stats().events);
+    /// }
+    /// ```
+    pub fn event_types(&self, name: &str, options: &WatchOptions) -> Result<Watch<String>, Error> {
+        validate_name(name)?;
+        let interrupt = options.interrupt()?;
+        let options = self.with_default_namespace(name, options);
+        let path = options.path(name);
+        let response = self.retry.run(|| self.get_interruptible(&path, &interrupt))?;
+        let reader = BufReader::new(body(response));
+        let format = self.format.clone();
+        watch::spawn_types(name, reader, interrupt, options, self.capacity, format)
     }
 
-    #[test]
-    fn cluster_get() {
-        let cluster = Cluster::new("http://duckduckgo.com").unwrap();
-        let response = cluster.get("/rust");
-        assert!(response.is_ok());
+    /// Watch resources with given `name` without spawning any thread. Events are read from the
+    /// connection only when the caller asks for the next one, so the caller fully controls when
+    /// reads happen. `WatchOptions::cancel` stops the reader even while it is blocked, but
+    /// `WatchOptions::idle_timeout` does not apply; `set_read_timeout` bounds every read instead.
+    ///
+    /// ```no_run
+    /// use kubewatch::{WatchEvent, WatchOptions};
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let mut reader = cluster
+    ///     .watch_reader::<WatchEvent<serde_json::Value>>("api/v1/pods", &WatchOptions::default())
+    ///     .unwrap();
+    /// while let Some(event) = reader.next() {
+    ///     println!("{:?}", event);
+    /// }
+    /// ```
+    pub fn watch_reader<Event>(&self,
+                               name: &str,
+                               options: &WatchOptions)
+                               -> Result<WatchReader<Event>, Error>
+        where Event: Deserialize
+    {
+        validate_name(name)?;
+        let interrupt = Interrupt::default();
+        if let Some(ref cancel) = options.cancel {
+            cancel.link(interrupt.clone());
+        }
+        let options = self.with_default_namespace(name, options);
+        let path = options.path(name);
+        let response = self.retry.run(|| self.get_interruptible(&path, &interrupt))?;
+        let reader = BufReader::new(body(response));
+        Ok(WatchReader::new(reader, interrupt, options, self.format.clone()))
     }
 
-    #[test]
-    fn cluster_get_invalid_url() {
-        let cluster = Cluster::new("http://does.not").unwrap();
-        let response = cluster.get("/exist");
-        assert!(matches!(response, Err(Error::HttpRequestFailed(_))));
+    /// Check that the server is reachable and accepts credentials of the client by reading its
+    /// version, e.g. to fail fast on startup before any watch is established. Unlike other
+    /// requests, the failure is reported as `ResolveFailed`, `TlsError`, `ConnectTimeout`,
+    /// `Unauthorized` or `Forbidden` where it applies. Failed checks are not retried.
+    ///
+    /// ```no_run
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// if let Err(e) = cluster.check() {
+    ///     panic!("cannot access the cluster: {}", e);
+    /// }
+    /// ```
+    pub fn check(&self) -> Result<(), Error> {
+        match self.get("version") {
+            Ok(_) => Ok(()),
+            Err(Error::HttpRequestFailed(hyper::Error::Io(e), _)) if is_resolve_failure(&e) => {
+                Err(Error::ResolveFailed(e))
+            }
+            Err(Error::UnexpectedStatus(StatusCode::Unauthorized, body)) => {
+                Err(Error::Unauthorized(body))
+            }
+            Err(Error::UnexpectedStatus(StatusCode::Forbidden, body)) => {
+                Err(Error::Forbidden(body))
+            }
+            Err(Error::ApiError(ref status)) if status.code == 401 => {
+                Err(Error::Unauthorized(status.message.clone()))
+            }
+            Err(Error::ApiError(ref status)) if status.code == 403 => {
+                Err(Error::Forbidden(status.message.clone()))
+            }
+            Err(e) => Err(e),
+        }
     }
 
-    impl Events for &'static str {
-        #[allow(unused_variables)] 
-        fn events<Event>(&self, name: &str) -> Result<Receiver<Result<Event, Error>>, Error>
-            where Event: Deserialize + Send + 'static
-        {
-            Ok(self.generator(self.bytes().into_iter().map(|b| Ok(b))))
+    /// Read current state of resources with given `name` without watching, and return them as
+    /// given `List` structure.
+    ///
+    /// ```no_run
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let pods = cluster.list::<serde_json::Value>("api/v1/pods").unwrap();
+    /// println!("{}", pods["metadata"]["resourceVersion"]);
+    /// ```
+    pub fn list<List>(&self, name: &str) -> Result<List, Error>
+        where List: Deserialize
+    {
+        validate_name(name)?;
+        self.get_json(&self.namespaced(name))
+    }
+
+    /// Read current state of resources with given `name` as a standard Kubernetes list, and return
+    /// its items together with the resource version of the list, from which a following watch
+    /// should start (see `WatchOptions::resource_version`).
+    ///
+    /// ```no_run
+    /// use kubewatch::{Events, WatchOptions};
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let (version, pods) = cluster.list_items::<serde_json::Value>("api/v1/pods").unwrap();
+    /// let options = WatchOptions { resource_version: Some(version), ..Default::default() };
+    /// let events = cluster.events_with_options::<serde_json::Value>("api/v1/pods", &options);
+    /// ```
+    pub fn list_items<Item>(&self, name: &str) -> Result<(String, Vec<Item>), Error>
+        where Item: Deserialize
+    {
+        let list: ItemList<Item> = self.list(name)?;
+        Ok((list.metadata.resource_version, list.items))
+    }
+
+    /// Read current state of a single `object` among resources with given `name`, and return it
+    /// as given `Object` structure.
+    ///
+    /// ```no_run
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let pod = cluster
+    ///     .get_object::<serde_json::Value>("api/v1/namespaces/default/pods", "nginx")
+    ///     .unwrap();
+    /// println!("{}", pod["status"]["phase"]);
+    /// ```
+    pub fn get_object<Object>(&self, name: &str, object: &str) -> Result<Object, Error>
+        where Object: Deserialize
+    {
+        validate_name(name)?;
+        if object.is_empty() || object == "." || object == ".." {
+            return Err(Error::InvalidResource(format!("{}/{}", name, object)));
         }
+        let object = utf8_percent_encode(object, PATH_SEGMENT_ENCODE_SET);
+        self.get_json(&format!("{}/{}", self.namespaced(name), object))
     }
 
-    #[derive(Deserialize, PartialEq, Eq, Debug)]
-    struct Point {
-        x: i32,
-        y: i32,
+    /// Create an object described by `body` among resources with given `name`, and return the
+    /// created object as given `Object` structure. Requests are not retried.
+    ///
+    /// ```no_run
+    /// #[macro_use]
+    /// extern crate serde_json;
+    /// extern crate kubewatch;
+    ///
+    /// fn main() {
+    ///     let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    ///     let config_map = json!({
+    ///         "apiVersion": "v1",
+    ///         "kind": "ConfigMap",
+    ///         "metadata": {"name": "settings"},
+    ///         "data": {"level": "debug"},
+    ///     });
+    ///     let created = cluster
+    ///         .post_object::<serde_json::Value, _>("api/v1/namespaces/default/configmaps",
+    ///                                              &config_map)
+    ///         .unwrap();
+    ///     println!("{}", created["metadata"]["uid"]);
+    /// }
+    /// ```
+    pub fn post_object<Object, Body>(&self, name: &str, body: &Body) -> Result<Object, Error>
+        where Object: Deserialize,
+              Body: Serialize
+    {
+        validate_name(name)?;
+        let content = serde_json::to_vec(body).map_err(Error::SerializationFailed)?;
+        let response = self.post(&self.namespaced(name), &content)?;
+        serde_json::from_reader(::body(response)).map_err(Error::DeserializationFailed)
     }
 
-    #[test]
-    fn events_generator() {
-        let mut events = r#"{"x": 1, "y": 2}{"x": 3, "y": 4}"#
-            .events::<Point>("points")
-            .unwrap()
-            .into_iter();
-        assert_eq!(events.next().unwrap().unwrap(), Point { x: 1, y: 2 });
-        assert_eq!(events.next().unwrap().unwrap(), Point { x: 3, y: 4 });
+    /// Read current state of resources with given `name` in pages of at most `limit` resources,
+    /// each returned as given `List` structure. Pages are requested lazily by the returned
+    /// iterator, so the whole collection does not have to be loaded at once.
+    ///
+    /// ```no_run
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// for page in cluster.list_paged::<serde_json::Value>("api/v1/pods", 500) {
+    ///     println!("{}", page.unwrap()["items"]);
+    /// }
+    /// ```
+    pub fn list_paged<List>(&self, name: &str, limit: u32) -> Pages<List>
+        where List: Deserialize
+    {
+        Pages::new(self.clone(), &self.namespaced(name), limit)
+    }
+
+    /// Read monitor of events like `Events::events_with_options`, but transparently re-establish
+    /// the watch whenever the server closes it. The watch is resumed from the resource version
+    /// of the last received object, if any. Failed reconnect attempts are reported through the
+    /// returned `Watch` and retried according to `ClusterBuilder::reconnect_policy`, by default
+    /// with increasing delay until the `Watch` is stopped. The watch ends with `Error::Expired`
+    /// once the resumed version is too old.
+    ///
+    /// ```no_run
+    /// use kubewatch::WatchOptions;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster
+    ///     .events_reconnecting::<serde_json::Value>("api/v1/pods", &WatchOptions::default())
+    ///     .unwrap();
+    /// ```
+    pub fn events_reconnecting<Event>(&self,
+                                      name: &str,
+                                      options: &WatchOptions)
+                                      -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static
+    {
+        validate_name(name)?;
+        let interrupt = options.interrupt()?;
+        let mut options = self.with_default_namespace(name, options);
+        let path = options.path(name);
+        let mut response = self.retry.run(|| self.get_interruptible(&path, &interrupt))?;
+        let cluster = self.clone();
+        let resource = name.to_owned();
+        let (tx, rx) = watch::channel(self.capacity);
+        let watch = Watch::new(rx, interrupt.clone()).with_running(options.running.clone());
+        let progress = watch.progress.clone();
+        progress.set_version(options.resource_version.clone());
+        watch::spawn_worker(name, move || loop {
+            let reader = BufReader::new(body(response));
+            let format = &*cluster.format;
+            let end = watch::forward(reader, &tx, &interrupt, &options, format, &progress);
+            if end == End::Stopped {
+                progress.finish(interrupt.is_triggered());
+                return;
+            }
+            options.resource_version = progress.version();
+            let policy = &cluster.reconnect;
+            let mut delay = policy.base_delay;
+            let mut attempt = 0;
+            response = loop {
+                thread::sleep(delay);
+                let stopped = || interrupt.is_triggered() || !options.is_running();
+                if stopped() {
+                    progress.finish(true);
+                    return;
+                }
+                let _slot = match policy.wait_for_slot(&cluster.reconnect_slots, stopped) {
+                    Some(slot) => slot,
+                    None => {
+                        progress.finish(true);
+                        return;
+                    }
+                };
+                attempt += 1;
+                debug!("reconnecting watch of {}, attempt {}", resource, attempt);
+                if let Some(ref on_reconnect) = policy.on_reconnect {
+                    on_reconnect(attempt);
+                }
+                match cluster.get_interruptible(&options.path(&resource), &interrupt) {
+                    Ok(response) => {
+                        progress.reconnected();
+                        break response;
+                    }
+                    Err(Error::Expired) => {
+                        progress.fail(&Error::Expired);
+                        let _ = tx.send(Err(Error::Expired));
+                        progress.finish(interrupt.is_triggered());
+                        return;
+                    }
+                    Err(err) => {
+                        let exhausted = policy.max_retries.is_some_and(|max| attempt >= max);
+                        let retry_after = match err {
+                            Error::RateLimited(after) => Some(after),
+                            _ => None,
+                        };
+                        if exhausted {
+                            progress.fail(&err);
+                        }
+                        if tx.send(Err(err)).is_err() || exhausted {
+                            progress.finish(interrupt.is_triggered());
+                            return;
+                        }
+                        delay = retry_after.unwrap_or(cmp::min(delay * 2, policy.max_delay));
+                    }
+                }
+            };
+        })?;
+        Ok(watch)
+    }
+
+    /// Watch resources with given `name` following the reflector pattern: all resources are
+    /// listed first and then watched from the resource version of the list. The list is repeated
+    /// every `interval`, and whenever the watch fails or is closed, after which the watch resumes
+    /// from the new version. Failed lists are reported through the returned `Watch` and retried
+    /// according to `ClusterBuilder::reconnect_policy`.
+    ///
+    /// ```no_run
+    /// use kubewatch::ResyncEvent;
+    /// use std::time::Duration;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster
+    ///     .watch_with_resync::<serde_json::Value>("api/v1/pods", Duration::from_secs(600))
+    ///     .unwrap();
+    /// for event in events {
+    ///     match event {
+    ///         Ok(ResyncEvent::List(pods)) => println!("{} pods", pods.len()),
+    ///         Ok(ResyncEvent::Event(event)) => println!("{:?}", event),
+    ///         Err(e) => println!("{}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn watch_with_resync<Item>(&self,
+                                   name: &str,
+                                   interval: Duration)
+                                   -> Result<Watch<ResyncEvent<Item>>, Error>
+        where Item: Deserialize + Send + 'static
+    {
+        validate_name(name)?;
+        let interrupt = Interrupt::default();
+        let (tx, rx) = watch::channel(self.capacity);
+        let watch = Watch::new(rx, interrupt.clone());
+        let cluster = self.clone();
+        let resource = name.to_owned();
+        watch::spawn_worker(name, move || {
+            let policy = &cluster.reconnect;
+            let mut delay = policy.base_delay;
+            while !interrupt.is_triggered() {
+                let mut options =
+                    cluster.with_default_namespace(&resource, &WatchOptions::default());
+                let list = cluster.get_json::<ItemList<Item>>(&options.resource_path(&resource));
+                let events = list.and_then(|list| {
+                    let version = list.metadata.resource_version;
+                    options.resource_version = Some(version).filter(|v| !v.is_empty());
+                    if tx.send(Ok(ResyncEvent::List(list.items))).is_err() {
+                        interrupt.trigger();
+                    }
+                    cluster.events_with_options::<WatchEvent<Item>>(&resource, &options)
+                });
+                let events = match events {
+                    Ok(events) => events,
+                    Err(_) if interrupt.is_triggered() => return,
+                    Err(e) => {
+                        if tx.send(Err(e)).is_err() {
+                            return;
+                        }
+                        thread::sleep(delay);
+                        delay = cmp::min(delay * 2, policy.max_delay);
+                        continue;
+                    }
+                };
+                delay = policy.base_delay;
+                let deadline = Instant::now() + interval;
+                while !interrupt.is_triggered() {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    // Wake up regularly to notice that the watch was stopped.
+                    match events.recv_timeout(cmp::min(deadline - now, RESYNC_POLL_INTERVAL)) {
+                        Ok(Ok(event)) => {
+                            if tx.send(Ok(ResyncEvent::Event(event))).is_err() {
+                                return;
+                            }
+                        }
+                        Ok(Err(e)) => {
+                            if tx.send(Err(e)).is_err() {
+                                return;
+                            }
+                            break;
+                        }
+                        Err(RecvTimeoutError::Timeout) => (),
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            }
+        })?;
+        Ok(watch)
+    }
+
+    /// Fill in the default namespace of the cluster, unless `options` specify their own or ask
+    /// for all namespaces, or resources `name` are not namespaced.
+    fn with_default_namespace(&self, name: &str, options: &WatchOptions) -> WatchOptions {
+        let mut options = options.clone();
+        if options.namespace.is_none() && !options.all_namespaces &&
+           is_namespaced(name, &self.cluster_scoped) {
+            options.namespace = self.namespace.clone();
+        }
+        options
     }
-}
\ No newline at end of file
+
+    /// Build path of resources `name` within the default namespace of the cluster.
+    fn namespaced(&self, name: &str) -> String {
+        self.with_default_namespace(name, &WatchOptions::default()).resource_path(name)
+    }
+
+    /// Replace the pool of connections shared by requests, so it follows current options.
+    fn reset_pool(&mut self) {
+        self.pool = Some(Arc::new(self.client(self.connector(), true)));
+    }
+
+    /// Run HTTP GET request on given path and deserialize its JSON response.
+    pub(crate) fn get_json<T>(&self, path: &str) -> Result<T, Error>
+        where T: Deserialize
+    {
+        let response = self.get(path)?;
+        serde_json::from_reader(body(response)).map_err(Error::DeserializationFailed)
+    }
+
+    /// Run HTTP GET request on given path (will be joined to `Cluster` URL). Connections are
+    /// reused among requests.
+    fn get(&self, path: &str) -> Result<Response, Error> {
+        let url = self.url(path)?;
+        match self.client.as_ref().or(self.pool.as_ref()) {
+            Some(client) => self.send(client, Method::Get, url, Json.accept(), None),
+            None => {
+                let client = self.client(self.connector(), false);
+                self.send(&client, Method::Get, url, Json.accept(), None)
+            }
+        }
+    }
+
+    /// Run HTTP POST request with JSON `content` on given path (will be joined to `Cluster`
+    /// URL). Connections are reused among requests.
+    fn post(&self, path: &str, content: &[u8]) -> Result<Response, Error> {
+        let url = self.url(path)?;
+        match self.client.as_ref().or(self.pool.as_ref()) {
+            Some(client) => self.send(client, Method::Post, url, Json.accept(), Some(content)),
+            None => {
+                let client = self.client(self.connector(), false);
+                self.send(&client, Method::Post, url, Json.accept(), Some(content))
+            }
+        }
+    }
+
+    /// Run HTTP GET request of a watch on given path, its connection will be shut down once
+    /// `interrupt` is triggered. Such connection is not reused by other requests. Connections of
+    /// a client given by `ClusterBuilder::client` cannot be shut down.
+    fn get_interruptible(&self, path: &str, interrupt: &Interrupt) -> Result<Response, Error> {
+        let response = self.send_interruptible(path, interrupt).and_then(|response| {
+            let content_type = response.headers
+                .get_raw("Content-Type")
+                .and_then(|values| values.first())
+                .map(|value| String::from_utf8_lossy(value).into_owned());
+            if self.format.accepts(content_type.as_deref()) {
+                Ok(response)
+            } else {
+                Err(Error::UnexpectedContentType(content_type.unwrap_or_default()))
+            }
+        });
+        match response {
+            Ok(_) => debug!("watch {} established", path),
+            Err(ref e) => warn!("watch {} failed: {}", path, e),
+        }
+        response
+    }
+
+    /// Send request of `get_interruptible`.
+    fn send_interruptible(&self, path: &str, interrupt: &Interrupt) -> Result<Response, Error> {
+        let url = self.url(path)?;
+        if let Some(ref client) = self.client {
+            return self.send(client, Method::Get, url, self.format.accept(), None);
+        }
+        #[cfg(unix)]
+        {
+            if let Some(ref socket) = self.unix_socket {
+                let client = self.unix_client(socket, Some(interrupt.clone()), false);
+                return self.send(&client, Method::Get, url, self.format.accept(), None);
+            }
+        }
+        let interrupt = interrupt.clone();
+        let connect = self.connector();
+        let connector = move |host: &str, port: u16, scheme: &str| {
+            let socket = connect(host, port, scheme)?;
+            interrupt.register(&socket)?;
+            Ok(socket)
+        };
+        let client = self.client(connector, false);
+        self.send(&client, Method::Get, url, self.format.accept(), None)
+    }
+
+    /// Build URL of given `path`, relative to the base path even if it starts with `/`.
+    fn url(&self, path: &str) -> Result<hyper::Url, Error> {
+        self.host.join(path.trim_start_matches('/')).map_err(Error::InvalidUrl)
+    }
+
+    /// Build function opening TCP connections, following the connect timeout, keep-alive and
+    /// SOCKS5 proxy.
+    fn connector(&self) -> impl Fn(&str, u16, &str) -> io::Result<TcpStream> + Send + Sync {
+        let timeout = self.connect_timeout;
+        let keep_alive = self.keep_alive;
+        let socks5 = self.socks5.clone();
+        move |host: &str, port: u16, _: &str| {
+            let socket = match socks5 {
+                Some(ref socks5) => socks5.connect(host, port, timeout)?,
+                None => connect(host, port, timeout)?,
+            };
+            if let Some(interval) = keep_alive {
+                keepalive::enable(&socket, interval)
+                    .map_err(|e| io::Error::new(e.kind(), KeepAliveFailed(e)))?;
+            }
+            Ok(socket)
+        }
+    }
+
+    /// Build client opening connections via `connector`, configured with TLS, proxy and timeout
+    /// options. If `pooled`, connections are kept alive and reused.
+    fn client<C>(&self, connector: C, pooled: bool) -> Client
+        where C: NetworkConnector<Stream = HttpStream> + Send + Sync + 'static
+    {
+        #[cfg(unix)]
+        {
+            if let Some(ref socket) = self.unix_socket {
+                return self.unix_client(socket, None, pooled);
+            }
+        }
+        let tls = NativeTlsClient::from(self.tls.clone());
+        let mut client = match self.proxy {
+            Some(ref proxy) => {
+                let host = proxy.host_str().unwrap_or_default().to_owned();
+                let port = proxy.port_or_known_default().unwrap_or(80);
+                Client::with_proxy_config(ProxyConfig::new("http", host, port, connector, tls))
+            }
+            None if pooled => {
+                let connector = HttpsConnector::with_connector(tls, connector);
+                Client::with_connector(Pool::with_connector(Default::default(), connector))
+            }
+            None => Client::with_connector(HttpsConnector::with_connector(tls, connector)),
+        };
+        client.set_redirect_policy(RedirectPolicy::FollowNone);
+        client.set_read_timeout(self.read_timeout);
+        client.set_write_timeout(self.write_timeout);
+        client
+    }
+
+    /// Build client opening connections to Unix domain `socket`, registered to `interrupt` if set.
+    /// If `pooled`, connections are kept alive and reused.
+    #[cfg(unix)]
+    fn unix_client(&self, socket: &Path, interrupt: Option<Interrupt>, pooled: bool) -> Client {
+        let connector = unix::UnixConnector {
+            path: socket.to_owned(),
+            interrupt,
+        };
+        let mut client = if pooled {
+            Client::with_connector(Pool::with_connector(Default::default(), connector))
+        } else {
+            Client::with_connector(connector)
+        };
+        client.set_redirect_policy(RedirectPolicy::FollowNone);
+        client.set_read_timeout(self.read_timeout);
+        client.set_write_timeout(self.write_timeout);
+        client
+    }
+
+    /// Send HTTP request to `url` via `client`, with headers configured for this `Cluster`. If
+    /// given, JSON `content` is sent as the body. Redirects are followed up to the configured
+    /// limit, credentials are dropped once redirected to another origin.
+    fn send(&self,
+            client: &Client,
+            mut method: Method,
+            mut url: hyper::Url,
+            accept: &str,
+            mut content: Option<&[u8]>)
+            -> Result<Response, Error> {
+        let mut headers = Headers::new();
+        headers.set(UserAgent(self.user_agent.clone()));
+        headers.set_raw("Accept", vec![accept.as_bytes().to_vec()]);
+        // Bearer token takes precedence, client certificates are presented by TLS regardless.
+        if let Some(ref token) = self.token {
+            headers.set(Authorization(Bearer { token: token.clone() }));
+        } else if let Some((ref username, ref password)) = self.basic_auth {
+            headers.set(Authorization(Basic {
+                username: username.clone(),
+                password: Some(password.clone()),
+            }));
+        }
+        if let Some(ref impersonation) = self.impersonation {
+            impersonation.set_headers(&mut headers);
+        }
+        if self.gzip {
+            headers.set(AcceptEncoding(vec![qitem(Encoding::Gzip)]));
+        }
+        if content.is_some() {
+            headers.set(ContentType::json());
+        }
+        headers.extend(self.headers.iter());
+        let mut redirects = 0;
+        let response = loop {
+            let mut request = client.request(method.clone(), url.clone()).headers(headers.clone());
+            if let Some(content) = content {
+                request = request.body(content);
+            }
+            let response = request.send().map_err(|e| match e {
+                    hyper::Error::Ssl(e) => Error::TlsError(e),
+                    hyper::Error::Io(e) if socks::is_proxy_failure(&e) => Error::ProxyFailed(e),
+                    hyper::Error::Io(ref e) if is_connect_timeout(e) => Error::ConnectTimeout,
+                    hyper::Error::Io(e) if is_keep_alive_failure(&e) => Error::KeepAliveFailed(e),
+                    hyper::Error::Io(ref e) if is_timeout(e) => Error::ReadTimeout,
+                    e => Error::HttpRequestFailed(e, Some(url.clone())),
+                })?;
+            let location = match redirect_location(&response, &url) {
+                Some(location) if self.max_redirects > 0 => location,
+                _ => break response,
+            };
+            if redirects == self.max_redirects {
+                return Err(Error::TooManyRedirects(location));
+            }
+            redirects += 1;
+            if !is_same_origin(&url, &location) {
+                headers.remove::<Authorization<Bearer>>();
+                // Impersonation grants privileges of another user just like the credentials do.
+                let impersonation: Vec<String> = headers.iter()
+                    .map(|header| header.name().to_owned())
+                    .filter(|name| name.to_ascii_lowercase().starts_with("impersonate-"))
+                    .collect();
+                for name in impersonation {
+                    headers.remove_raw(&name);
+                }
+            }
+            // Like browsers, switch POST to GET unless the redirect asks to preserve the method.
+            if method == Method::Post && response.status != StatusCode::TemporaryRedirect &&
+               response.status != StatusCode::PermanentRedirect {
+                method = Method::Get;
+                content = None;
+                headers.remove::<ContentType>();
+            }
+            url = location;
+        };
+        if response.status == StatusCode::Gone {
+            return Err(Error::Expired);
+        }
+        if response.status == StatusCode::TooManyRequests {
+            return Err(Error::RateLimited(retry_after(&response.headers)));
+        }
+        if !response.status.is_success() {
+            let status = response.status;
+            let mut content = Vec::new();
+            let _ = body(response).read_to_end(&mut content);
+            if let Some(mut api_status) = api_status(&content) {
+                if api_status.code == 0 {
+                    api_status.code = status.to_u16();
+                }
+                return Err(Error::ApiError(api_status));
+            }
+            let content = String::from_utf8_lossy(&content).into_owned();
+            return Err(Error::UnexpectedStatus(status, content));
+        }
+        Ok(response)
+    }
+}
+
+/// Envelope of lists returned by the server.
+#[derive(Deserialize)]
+struct ItemList<Item> {
+    #[serde(default)]
+    metadata: ListMeta,
+    #[serde(default = "Vec::new")]
+    items: Vec<Item>,
+}
+
+/// Metadata of lists returned by the server.
+#[derive(Deserialize, Default)]
+struct ListMeta {
+    #[serde(rename = "resourceVersion", default)]
+    resource_version: String,
+}
+
+/// Identity the client acts as instead of its own, see `Cluster::set_impersonation`.
+///
+/// ```
+/// use kubewatch::Impersonation;
+///
+/// let mut cluster = kubewatch::Cluster::with_token("https://127.0.0.1:6443", "admin").unwrap();
+/// let mut impersonation = Impersonation::user("jane");
+/// impersonation.groups = vec!["developers".to_owned(), "system:authenticated".to_owned()];
+/// impersonation.extra.insert("scopes".to_owned(), vec!["view".to_owned()]);
+/// cluster.set_impersonation(impersonation);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Impersonation {
+    /// Name of the impersonated user, sent as `Impersonate-User`.
+    pub user: String,
+    /// Groups of the user, every one sent as a separate `Impersonate-Group` header.
+    pub groups: Vec<String>,
+    /// Extra fields of the user, sent as `Impersonate-Extra-<key>` headers with one value per
+    /// header. Keys are percent-encoded where they contain characters not allowed in headers.
+    pub extra: BTreeMap<String, Vec<String>>,
+}
+
+impl Impersonation {
+    /// Impersonate `user` without any groups or extra fields.
+    pub fn user(user: &str) -> Impersonation {
+        Impersonation { user: user.to_owned(), ..Default::default() }
+    }
+
+    /// Add headers of the impersonation to `headers`.
+    fn set_headers(&self, headers: &mut Headers) {
+        headers.set_raw("Impersonate-User", vec![self.user.as_bytes().to_vec()]);
+        if !self.groups.is_empty() {
+            let groups = self.groups.iter().map(|group| group.as_bytes().to_vec()).collect();
+            headers.set_raw("Impersonate-Group", groups);
+        }
+        for (key, values) in &self.extra {
+            let key = utf8_percent_encode(key, PATH_SEGMENT_ENCODE_SET);
+            let values = values.iter().map(|value| value.as_bytes().to_vec()).collect();
+            headers.set_raw(format!("Impersonate-Extra-{}", key), values);
+        }
+    }
+}
+
+/// Status and headers of the response sent by the server when a watch was established.
+#[derive(Debug, Clone)]
+pub struct ResponseInfo {
+    /// HTTP status of the response.
+    pub status: StatusCode,
+    /// HTTP headers of the response.
+    pub headers: Headers,
+}
+
+/// Options narrowing down which events should be watched and altering how they are processed.
+#[derive(Debug, Default, Clone)]
+pub struct WatchOptions {
+    /// Watch only resources in given namespace. If not set, the default namespace of the cluster
+    /// is used, see `Cluster::set_namespace`.
+    pub namespace: Option<String>,
+    /// Watch resources in all namespaces (e.g. `api/v1/pods` instead of
+    /// `api/v1/namespaces/default/pods`), ignoring both `namespace` and the default namespace of
+    /// the cluster. Every object still carries its own namespace in its metadata.
+    pub all_namespaces: bool,
+    /// Watch only resources matching given label selector (e.g. `app=nginx,tier!=db`).
+    pub label_selector: Option<String>,
+    /// Watch only resources matching given field selector (e.g. `status.phase=Running`).
+    pub field_selector: Option<String>,
+    /// Start watching from given resource version, only changes made after it will be received.
+    pub resource_version: Option<String>,
+    /// Ask the server to close the watch after given number of seconds, which can be combined
+    /// with `Cluster::events_reconnecting` to periodically re-establish it.
+    pub timeout_seconds: Option<u32>,
+    /// Ask the server to send `WatchEvent::Bookmark` events, marking up to which resource version
+    /// the watch progressed even if no watched object changed.
+    pub allow_watch_bookmarks: bool,
+    /// Additional query parameters appended after the other options, e.g. those of newer
+    /// Kubernetes versions which have no option of their own. They are percent-encoded.
+    pub query: Vec<(String, String)>,
+    /// Silently drop events which failed to deserialize instead of reporting them as errors.
+    /// Either way, the watch continues with the following event.
+    pub skip_errors: bool,
+    /// Query parameters requesting the stream, replacing `watch=true`. This allows reading
+    /// other streaming endpoints, e.g. `[("follow", "true")]` for logs of a pod. Other options
+    /// are still added to the query.
+    pub stream_query: Option<Vec<(String, String)>>,
+    /// Maximal size of a single event in bytes. Larger events are skipped without buffering them
+    /// and reported as `Error::FrameTooLarge`, so huge objects cannot exhaust memory.
+    pub max_event_bytes: Option<usize>,
+    /// Maximal nesting of objects and arrays within a single event. Deeper events are reported as
+    /// `Error::InvalidEvent` and the watch continues. The JSON parser itself rejects
+    /// events nested deeper than 128 levels, so only lower limits have an effect.
+    pub max_json_depth: Option<usize>,
+    /// Flag checked by the worker between events, the watch stops once it is cleared. Sharing
+    /// the flag among watches allows stopping all of them at once, e.g. on `SIGTERM`, see
+    /// `Watch::shut_down`. Watches blocked waiting for the next event stop only once it arrives.
+    pub running: Option<Arc<AtomicBool>>,
+    /// Drop events repeating the last seen resource version of their object (matched by UID),
+    /// e.g. sent again after `Cluster::events_reconnecting` re-established the watch. Only
+    /// versions of objects which were not deleted are remembered.
+    pub dedup: bool,
+    /// Token stopping the watch once cancelled, e.g. when a deadline of the request it serves
+    /// passes. Its connection is closed right away, as if `Watch::stop` was called.
+    pub cancel: Option<CancelToken>,
+    /// Maximal time between two events. Once it passes without any event, the connection is
+    /// closed and `Error::IdleTimeout` is delivered. Unlike the read timeout, it applies also to
+    /// servers which keep sending data without completing an event. Watches of
+    /// `Cluster::events_reconnecting` are then re-established, others end.
+    pub idle_timeout: Option<Duration>,
+    /// Maximal duration of the whole watch. Once it passes, the watch stops as if `Watch::stop`
+    /// was called: its connection is closed and iterating over it ends without an error.
+    pub max_duration: Option<Duration>,
+}
+
+impl WatchOptions {
+    /// Check whether the `running` flag, if any, was not cleared yet.
+    fn is_running(&self) -> bool {
+        self.running.as_ref().is_none_or(|running| running.load(Ordering::SeqCst))
+    }
+
+    /// Create interrupt of a new watch, triggered by the cancel token if any or once the maximal
+    /// duration passes. Fails if the thread enforcing the duration cannot be spawned.
+    fn interrupt(&self) -> Result<Interrupt, Error> {
+        let interrupt = Interrupt::default();
+        if let Some(ref cancel) = self.cancel {
+            cancel.link(interrupt.clone());
+        }
+        if let Some(duration) = self.max_duration {
+            interrupt.trigger_after(duration)?;
+        }
+        Ok(interrupt)
+    }
+
+    /// Build path of resource `name` (e.g. `api/v1/pods`) within the watched namespace, without
+    /// the query.
+    fn resource_path(&self, name: &str) -> String {
+        match self.namespace {
+            Some(ref namespace) if !self.all_namespaces && is_namespaced(name, &[]) => {
+                let namespace = utf8_percent_encode(namespace, PATH_SEGMENT_ENCODE_SET);
+                match name.rfind('/') {
+                    Some(i) => {
+                        format!("{}/namespaces/{}/{}", &name[..i], namespace, &name[i + 1..])
+                    }
+                    None => format!("namespaces/{}/{}", namespace, name),
+                }
+            }
+            _ => name.to_owned(),
+        }
+    }
+
+    /// Build path of watched resource `name` (e.g. `api/v1/pods`) including the query.
+    fn path(&self, name: &str) -> String {
+        let path = self.resource_path(name);
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        match self.stream_query {
+            Some(ref stream_query) => {
+                query.extend_pairs(stream_query);
+            }
+            None => {
+                query.append_pair("watch", "true");
+            }
+        }
+        if let Some(ref selector) = self.label_selector {
+            query.append_pair("labelSelector", selector);
+        }
+        if let Some(ref selector) = self.field_selector {
+            query.append_pair("fieldSelector", selector);
+        }
+        if let Some(ref version) = self.resource_version {
+            query.append_pair("resourceVersion", version);
+        }
+        if let Some(timeout) = self.timeout_seconds {
+            query.append_pair("timeoutSeconds", &timeout.to_string());
+        }
+        if self.allow_watch_bookmarks {
+            query.append_pair("allowWatchBookmarks", "true");
+        }
+        query.extend_pairs(&self.query);
+        format!("{}?{}", path, query.finish())
+    }
+}
+
+/// This trait is used to deserialize input stream and return respective Rust structs.
+///
+/// Events are deserialized by a worker thread and then sent to the consumer, so they must own
+/// all their data. Types with borrowed lifetimes are rejected:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate serde_derive;
+/// extern crate kubewatch;
+///
+/// use kubewatch::{Cluster, Events};
+/// use std::marker::PhantomData;
+///
+/// #[derive(Deserialize)]
+/// struct Pod<'a> {
+///     name: PhantomData<&'a str>,
+/// }
+///
+/// fn pods<'a>(cluster: &Cluster) {
+///     let events = cluster.events::<Pod<'a>>("api/v1/pods");
+/// }
+///
+/// fn main() {
+///     pods(&Cluster::new("http://127.0.0.1:8080").unwrap());
+/// }
+/// ```
+pub trait Events {
+    /// Read monitor of events with given `name` and return them as given `Event` structure.
+    fn events<Event>(&self, name: &str) -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static
+    {
+        self.events_with_options(name, &WatchOptions::default())
+    }
+
+    /// Read monitor of events with given `name` narrowed down by `options` and return them as
+    /// given `Event` structure.
+    ///
+    /// ```no_run
+    /// use kubewatch::{Events, WatchOptions};
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let options = WatchOptions { namespace: Some("default".to_owned()), ..Default::default() };
+    /// let events = cluster.events_with_options::<serde_json::Value>("api/v1/pods", &options);
+    /// ```
+    fn events_with_options<Event>(&self,
+                                  name: &str,
+                                  options: &WatchOptions)
+                                  -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static;
+
+    /// Read monitor of events with given `name` and return them as `WatchEvent` frames carrying
+    /// changed objects of type `Object`.
+    ///
+    /// ```no_run
+    /// use kubewatch::{Events, WatchEvent};
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster.events_typed::<serde_json::Value>("api/v1/pods").unwrap();
+    /// for event in events {
+    ///     if let Ok(WatchEvent::Added(pod)) = event {
+    ///         println!("{}", pod["metadata"]["name"]);
+    ///     }
+    /// }
+    /// ```
+    fn events_typed<Object>(&self,
+                            name: &str)
+                            -> Result<Watch<WatchEvent<Object>>, Error>
+        where Object: Deserialize + Send + 'static
+    {
+        self.events(name)
+    }
+
+    /// Read monitor of events of a single object named `object` among resources with given
+    /// `name`.
+    ///
+    /// ```no_run
+    /// use kubewatch::{Events, WatchEvent};
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster
+    ///     .watch_one::<WatchEvent<serde_json::Value>>("api/v1/namespaces/default/pods", "nginx")
+    ///     .unwrap();
+    /// ```
+    fn watch_one<Event>(&self, name: &str, object: &str) -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static
+    {
+        let options = WatchOptions {
+            field_selector: Some(format!("metadata.name={}", escape_field_value(object))),
+            ..Default::default()
+        };
+        self.events_with_options(name, &options)
+    }
+
+    /// Read monitors of events of all resources in `names` merged into a single `Watch`. Every
+    /// event is paired with the name of the resource it belongs to.
+    ///
+    /// ```no_run
+    /// use kubewatch::Events;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let names = ["api/v1/pods", "api/v1/services", "api/v1/endpoints"];
+    /// let events = cluster.events_multi::<serde_json::Value>(&names).unwrap();
+    /// for event in events {
+    ///     let (name, event) = event.unwrap();
+    ///     println!("{}: {}", name, event["type"]);
+    /// }
+    /// ```
+    fn events_multi<Event>(&self, names: &[&str]) -> Result<Watch<(String, Event)>, Error>
+        where Event: Deserialize + Send + 'static
+    {
+        let watches = names.iter()
+            .map(|name| Ok((name.to_string(), self.events(name)?)))
+            .collect::<Result<_, Error>>()?;
+        watch::merge(watches)
+    }
+
+    /// Helper which reads a byte iterator, deserializes it and return respective structures.
+    fn generator<Event, Iter>(&self, iter: Iter) -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static,
+              Iter: Iterator<Item = io::Result<u8>> + Send + 'static
+    {
+        let reader = BufReader::new(IterReader(iter));
+        let options = WatchOptions::default();
+        watch::spawn("generator", reader, Interrupt::default(), options, None, Arc::new(Json))
+    }
+
+    /// Helper which reads events from `reader` chunk by chunk, deserializes them and return
+    /// respective structures. It is much faster than `generator` for high-throughput streams.
+    fn generator_from_reader<Event, R>(&self, reader: R) -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static,
+              R: Read + Send + 'static
+    {
+        let reader = BufReader::new(reader);
+        let options = WatchOptions::default();
+        watch::spawn("generator", reader, Interrupt::default(), options, None, Arc::new(Json))
+    }
+}
+
+/// Read event monitor from Kubernetes API server.
+impl Events for Cluster {
+    fn events_with_options<Event>(&self,
+                                  name: &str,
+                                  options: &WatchOptions)
+                                  -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static
+    {
+        self.events_with_response(name, options).map(|(_, watch)| watch)
+    }
+}
+
+/// Check that resource `name` is an API path, optionally starting with `/`, which cannot escape
+/// its segments, e.g. by `..` or a query. Other path characters such as `:`, `@` or
+/// percent-encoded bytes are allowed.
+fn validate_name(name: &str) -> Result<(), Error> {
+    let valid_segment = |segment: &str| {
+        // Percent-encoded dots are treated as dots when the URL is resolved.
+        let decoded = segment.to_ascii_lowercase().replace("%2e", ".");
+        !segment.is_empty() && decoded != "." && decoded != ".." &&
+        !segment.chars().any(|c| c == '?' || c == '#' || c.is_whitespace() || c.is_control())
+    };
+    let path = name.strip_prefix('/').unwrap_or(name);
+    if path.split('/').all(valid_segment) {
+        Ok(())
+    } else {
+        Err(Error::InvalidResource(name.to_owned()))
+    }
+}
+
+/// Parse `Status` object from body of a failed response, if it carries one.
+fn api_status(body: &[u8]) -> Option<Status> {
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+    if body.get("kind").and_then(serde_json::Value::as_str) != Some("Status") {
+        return None;
+    }
+    serde_json::from_value(body).ok()
+}
+
+/// Target of `response` redirecting the request of `url`, if it is a redirect.
+fn redirect_location(response: &Response, url: &hyper::Url) -> Option<hyper::Url> {
+    if !response.status.is_redirection() || response.status == StatusCode::NotModified {
+        return None;
+    }
+    let location = response.headers.get::<Location>()?;
+    url.join(location).ok()
+}
+
+/// Check whether a namespace can be inserted into path of resources `name`, i.e. it does not
+/// include one already and the resources are neither built-in nor listed in `cluster_scoped`.
+fn is_namespaced(name: &str, cluster_scoped: &[String]) -> bool {
+    let path = name.split('?').next().unwrap_or_default();
+    let mut segments: Vec<&str> = path.split('/').collect();
+    let resource = segments.pop().unwrap_or_default();
+    !segments.contains(&"namespaces") && !CLUSTER_SCOPED.contains(&resource) &&
+    !cluster_scoped.iter().any(|scoped| scoped == resource)
+}
+
+/// Check whether URLs `a` and `b` have the same scheme, host and port.
+fn is_same_origin(a: &hyper::Url, b: &hyper::Url) -> bool {
+    a.scheme() == b.scheme() && a.host_str() == b.host_str() &&
+    a.port_or_known_default() == b.port_or_known_default()
+}
+
+/// Time to wait before retrying a rate limited request, as requested by the `Retry-After` header
+/// in seconds. Defaults to one second if the header is missing or specifies a date.
+fn retry_after(headers: &Headers) -> Duration {
+    let seconds = headers.get_raw("Retry-After")
+        .and_then(|values| values.first())
+        .and_then(|value| str::from_utf8(value).ok())
+        .and_then(|value| value.trim().parse().ok());
+    Duration::from_secs(seconds.unwrap_or(1))
+}
+
+/// Replace path of `host` by `base_path`, with exactly one leading and trailing slash, so paths
+/// of resources can be joined to it.
+fn set_base_path(host: &mut hyper::Url, base_path: &str) {
+    let base_path = base_path.trim_matches('/');
+    if base_path.is_empty() {
+        host.set_path("/");
+    } else {
+        host.set_path(&format!("/{}/", base_path));
+    }
+}
+
+/// Marker of I/O errors caused by an expired connect timeout.
+#[derive(Debug)]
+struct ConnectTimedOut;
+
+impl fmt::Display for ConnectTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "connect timed out")
+    }
+}
+
+impl StdError for ConnectTimedOut {}
+
+/// Marker of I/O errors caused by a failed resolution of the host name, wrapping the original
+/// error.
+#[derive(Debug)]
+struct ResolutionFailed(io::Error);
+
+impl fmt::Display for ResolutionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl StdError for ResolutionFailed {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Marker of I/O errors caused by a failure to enable TCP keep-alive, wrapping the original
+/// error.
+#[derive(Debug)]
+struct KeepAliveFailed(io::Error);
+
+impl fmt::Display for KeepAliveFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl StdError for KeepAliveFailed {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Connect to `host` and `port` within `timeout`, if set. Timed out attempts fail with an error
+/// recognized by `is_connect_timeout`, failed resolution of the host by `is_resolve_failure`.
+fn connect(host: &str, port: u16, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let addresses = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| io::Error::new(e.kind(), ResolutionFailed(e)))?;
+    let mut last_error = None;
+    for address in addresses {
+        let result = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(&address, timeout),
+            None => TcpStream::connect(address),
+        };
+        match result {
+            Ok(socket) => return Ok(socket),
+            Err(ref e) if is_timeout(e) => {
+                last_error = Some(io::Error::new(io::ErrorKind::TimedOut, ConnectTimedOut))
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "host resolved to no addresses")
+    }))
+}
+
+/// Check whether connecting failed due to an expired timeout.
+fn is_connect_timeout(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<ConnectTimedOut>())
+}
+
+/// Check whether TCP keep-alive could not be enabled on a new connection.
+fn is_keep_alive_failure(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<KeepAliveFailed>())
+}
+
+/// Check whether connecting failed because the host name could not be resolved.
+fn is_resolve_failure(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<ResolutionFailed>())
+}
+
+/// Escape characters with special meaning in field selectors.
+fn escape_field_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || c == ',' || c == '=' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Body of `response`, decompressed according to its `Content-Encoding`.
+fn body(response: Response) -> Box<dyn Read + Send> {
+    let encodings = response.headers.get::<ContentEncoding>();
+    if encodings.is_some_and(|encodings| encodings.contains(&Encoding::Gzip)) {
+        Box::new(GzipDecoder::new(BufReader::new(response)))
+    } else {
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc, Arc};
+
+    #[test]
+    fn cluster() {
+        let cluster = Cluster::new("http://rust-lang.org");
+        assert!(cluster.is_ok());
+    }
+
+    #[test]
+    fn validate_name() {
+        assert!(super::validate_name("pods").is_ok());
+        assert!(super::validate_name("apis/example.com/v1/namespaces/default/widgets").is_ok());
+        for name in &["/api/v1/pods",
+                      "apis/rbac.authorization.k8s.io/v1/clusterroles/system:controller:x",
+                      "api/v1/nodes/system:node",
+                      "api/v1/namespaces/kube%20system/pods",
+                      "api/v1/users/jane@example.com"] {
+            assert!(super::validate_name(name).is_ok(), "{}", name);
+        }
+        for name in &["", "/", "//api/v1/pods", "api//pods", "api/v1/../v2/pods", "api/%2E%2e/pods",
+                      "api/v1/./pods", "pods?watch=false", "pods#x", "pods log", "pods\tlog",
+                      "pods\u{7f}"] {
+            assert!(matches!(super::validate_name(name), Err(Error::InvalidResource(_))),
+                    "{}",
+                    name);
+        }
+    }
+
+    #[test]
+    fn events_invalid_resource() {
+        let cluster = Cluster::new("http://127.0.0.1:8080").unwrap();
+        let events = cluster.events::<serde_json::Value>("../healthz");
+        assert!(matches!(events, Err(Error::InvalidResource(ref name)) if name == "../healthz"));
+        let list = cluster.list::<serde_json::Value>("pods?watch=true");
+        assert!(matches!(list, Err(Error::InvalidResource(_))));
+    }
+
+    #[test]
+    fn error_display() {
+        let error = Error::UnexpectedStatus(StatusCode::Forbidden, "pods is forbidden".to_owned());
+        assert_eq!(error.to_string(), "unexpected status 403 Forbidden: pods is forbidden");
+        assert!(error.source().is_none());
+    }
+
+    #[test]
+    fn error_source() {
+        let reset = io::Error::new(io::ErrorKind::ConnectionReset, "connection reset");
+        let error = Error::StreamIo(reset);
+        assert_eq!(error.to_string(), "failed to read event stream: connection reset");
+        assert_eq!(error.source().unwrap().to_string(), "connection reset");
+    }
+
+    #[test]
+    fn error_from() {
+        fn parse() -> Result<serde_json::Value, Error> {
+            hyper::Url::parse("http://127.0.0.1:8080")?;
+            Ok(serde_json::from_str("{")?)
+        }
+        assert!(matches!(parse(), Err(Error::DeserializationFailed(_))));
+    }
+
+    #[test]
+    fn error_from_hyper() {
+        fn request() -> Result<(), Error> {
+            Err(hyper::Error::Io(io::Error::other("connection reset")))?;
+            Ok(())
+        }
+        assert!(matches!(request(), Err(Error::HttpRequestFailed(hyper::Error::Io(_), None))));
+        let error = Error::from(hyper::Error::Method);
+        assert_eq!(error.to_string(), format!("HTTP request failed: {}", hyper::Error::Method));
+    }
+
+    #[test]
+    fn cluster_with_token() {
+        let cluster = Cluster::with_token("http://rust-lang.org", "secret").unwrap();
+        assert_eq!(cluster.token, Some("secret".to_owned()));
+    }
+
+    #[test]
+    fn cluster_https() {
+        let cluster = Cluster::new("https://rust-lang.org");
+        assert!(cluster.is_ok());
+    }
+
+    #[test]
+    fn cluster_with_invalid_ca() {
+        let cluster = Cluster::with_ca("https://rust-lang.org", b"not a certificate");
+        assert!(matches!(cluster, Err(Error::TlsError(_))));
+    }
+
+    #[test]
+    fn cluster_with_invalid_client_cert() {
+        let cluster = Cluster::with_client_cert("https://rust-lang.org", b"cert", b"key");
+        assert!(matches!(cluster, Err(Error::InvalidClientCertificate(_))));
+    }
+
+    #[test]
+    fn cluster_invalid_url() {
+        let cluster = Cluster::new("123.456.789.000");
+        assert!(matches!(cluster, Err(Error::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn cluster_get() {
+        let cluster = Cluster::new("http://duckduckgo.com").unwrap();
+        let response = cluster.get("/rust");
+        assert!(response.is_ok());
+    }
+
+    #[test]
+    fn cluster_get_invalid_url() {
+        let cluster = Cluster::new("http://does.not").unwrap();
+        match cluster.get("/exist") {
+            Err(Error::HttpRequestFailed(_, Some(url))) => {
+                assert_eq!(url.as_str(), "http://does.not/exist")
+            }
+            response => panic!("unexpected response {:?}", response.map(|r| r.status)),
+        }
+    }
+
+    #[test]
+    fn watch_options_path() {
+        let options = WatchOptions::default();
+        assert_eq!(options.path("api/v1/pods"), "api/v1/pods?watch=true");
+    }
+
+    #[test]
+    fn watch_options_path_namespaced() {
+        let options = WatchOptions {
+            namespace: Some("kube system".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"),
+                   "api/v1/namespaces/kube%20system/pods?watch=true");
+        assert_eq!(options.path("pods"), "namespaces/kube%20system/pods?watch=true");
+    }
+
+    #[test]
+    fn watch_options_path_label_selector() {
+        let options = WatchOptions {
+            label_selector: Some("app=nginx,tier!=db".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"),
+                   "api/v1/pods?watch=true&labelSelector=app%3Dnginx%2Ctier%21%3Ddb");
+    }
+
+    #[test]
+    fn watch_options_path_field_selector() {
+        let options = WatchOptions {
+            field_selector: Some("status.phase=Running,spec.nodeName=n1".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"),
+                   "api/v1/pods?watch=true&fieldSelector=\
+                    status.phase%3DRunning%2Cspec.nodeName%3Dn1");
+    }
+
+    #[test]
+    fn watch_options_path_resource_version() {
+        let options = WatchOptions {
+            resource_version: Some("12345".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"),
+                   "api/v1/pods?watch=true&resourceVersion=12345");
+    }
+
+    #[test]
+    fn watch_options_path_query() {
+        let options = WatchOptions {
+            timeout_seconds: Some(60),
+            query: vec![("sendInitialEvents".to_owned(), "true".to_owned()),
+                        ("x".to_owned(), "a&b".to_owned())],
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"),
+                   "api/v1/pods?watch=true&timeoutSeconds=60&sendInitialEvents=true&x=a%26b");
+    }
+
+    #[test]
+    fn watch_options_path_stream_query() {
+        let options = WatchOptions {
+            stream_query: Some(vec![("follow".to_owned(), "true".to_owned()),
+                                    ("container".to_owned(), "a b".to_owned())]),
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/namespaces/default/pods/nginx/log"),
+                   "api/v1/namespaces/default/pods/nginx/log?follow=true&container=a+b");
+    }
+
+    #[test]
+    fn cluster_base_path() {
+        let mut cluster = Cluster::new("http://127.0.0.1:8001/k8s-api").unwrap();
+        assert_eq!(cluster.url("api/v1/pods").unwrap().as_str(),
+                   "http://127.0.0.1:8001/k8s-api/api/v1/pods");
+        cluster.set_base_path("/proxy//");
+        assert_eq!(cluster.url("/api/v1/pods").unwrap().as_str(),
+                   "http://127.0.0.1:8001/proxy/api/v1/pods");
+        cluster.set_base_path("");
+        assert_eq!(cluster.url("api/v1/pods").unwrap().as_str(),
+                   "http://127.0.0.1:8001/api/v1/pods");
+    }
+
+    #[test]
+    fn is_connect_timeout() {
+        let timed_out = io::Error::new(io::ErrorKind::TimedOut, ConnectTimedOut);
+        assert!(super::is_connect_timeout(&timed_out));
+        assert!(!super::is_connect_timeout(&io::ErrorKind::TimedOut.into()));
+    }
+
+    #[test]
+    fn escape_field_value() {
+        assert_eq!(super::escape_field_value("nginx"), "nginx");
+        assert_eq!(super::escape_field_value(r"a,b=c\d"), r"a\,b\=c\\d");
+    }
+
+    #[test]
+    fn watch_one() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            assert_eq!(request.uri.to_string(),
+                       "/api/v1/pods?watch=true&fieldSelector=metadata.name%3Dnginx");
+            response.send(br#"{"type": "ADDED", "object": {"x": 1, "y": 2}}"#).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let events = cluster.watch_one::<WatchEvent<Point>>("api/v1/pods", "nginx").unwrap();
+        assert_eq!(events.recv().unwrap().unwrap(), WatchEvent::Added(Point { x: 1, y: 2 }));
+    }
+
+    #[test]
+    fn watch_options_path_timeout_seconds() {
+        let options = WatchOptions {
+            timeout_seconds: Some(300),
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"), "api/v1/pods?watch=true&timeoutSeconds=300");
+    }
+
+    #[test]
+    fn for_namespace() {
+        let cluster = Cluster::new("http://127.0.0.1:8080").unwrap();
+        let clone = cluster.for_namespace("kube-system");
+        assert_eq!(clone.namespace, Some("kube-system".to_owned()));
+        assert_eq!(cluster.namespace, None);
+        assert!(Arc::ptr_eq(clone.pool.as_ref().unwrap(), cluster.pool.as_ref().unwrap()));
+        assert!(Arc::ptr_eq(&clone.reconnect_slots, &cluster.reconnect_slots));
+    }
+
+    #[test]
+    fn default_namespace() {
+        let mut cluster = Cluster::new("http://127.0.0.1:8080").unwrap();
+        cluster.set_namespace("default");
+        let options = cluster.with_default_namespace("api/v1/pods", &WatchOptions::default());
+        assert_eq!(options.namespace, Some("default".to_owned()));
+        let options = WatchOptions {
+            namespace: Some("kube-system".to_owned()),
+            ..Default::default()
+        };
+        let options = cluster.with_default_namespace("api/v1/pods", &options);
+        assert_eq!(options.namespace, Some("kube-system".to_owned()));
+        let options = WatchOptions {
+            all_namespaces: true,
+            ..Default::default()
+        };
+        assert_eq!(cluster.with_default_namespace("api/v1/pods", &options).namespace, None);
+    }
+
+    #[test]
+    fn default_namespace_not_namespaced() {
+        let mut cluster = Cluster::new("http://127.0.0.1:8080").unwrap();
+        cluster.set_namespace("default");
+        cluster.set_cluster_scoped("widgets");
+        assert_eq!(cluster.namespaced("api/v1/pods"), "api/v1/namespaces/default/pods");
+        assert_eq!(cluster.namespaced("pods"), "namespaces/default/pods");
+        assert_eq!(cluster.namespaced("api/v1/nodes"), "api/v1/nodes");
+        assert_eq!(cluster.namespaced("api/v1/namespaces"), "api/v1/namespaces");
+        assert_eq!(cluster.namespaced("api/v1/nodes?labelSelector=a"),
+                   "api/v1/nodes?labelSelector=a");
+        assert_eq!(cluster.namespaced("apis/example.com/v1/widgets"),
+                   "apis/example.com/v1/widgets");
+        assert_eq!(cluster.namespaced("api/v1/namespaces/kube-system/pods"),
+                   "api/v1/namespaces/kube-system/pods");
+        let options = WatchOptions {
+            namespace: Some("kube-system".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(options.resource_path("api/v1/namespaces/default/pods"),
+                   "api/v1/namespaces/default/pods");
+        assert_eq!(options.resource_path("api/v1/nodes"), "api/v1/nodes");
+    }
+
+    #[test]
+    fn default_namespace_requests() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            let uri = format!(r#"{{"uri": "{}"}}"#, request.uri);
+            response.send(uri.as_bytes()).unwrap();
+        });
+        let cluster = ClusterBuilder::new()
+            .host(&host)
+            .namespace("default")
+            .cluster_scoped("widgets")
+            .build()
+            .unwrap();
+        let uri = |object: Result<serde_json::Value, Error>| {
+            object.unwrap()["uri"].as_str().unwrap().to_owned()
+        };
+        let body = serde_json::Value::Null;
+        assert_eq!(uri(cluster.list("api/v1/pods")), "/api/v1/namespaces/default/pods");
+        assert_eq!(uri(cluster.list("api/v1/nodes")), "/api/v1/nodes");
+        assert_eq!(uri(cluster.list("apis/example.com/v1/widgets")),
+                   "/apis/example.com/v1/widgets");
+        assert_eq!(uri(cluster.get_object("api/v1/pods", "nginx")),
+                   "/api/v1/namespaces/default/pods/nginx");
+        assert_eq!(uri(cluster.get_object("api/v1/namespaces", "default")),
+                   "/api/v1/namespaces/default");
+        assert_eq!(uri(cluster.post_object("api/v1/configmaps", &body)),
+                   "/api/v1/namespaces/default/configmaps");
+        assert_eq!(uri(cluster.post_object("api/v1/namespaces/a/configmaps", &body)),
+                   "/api/v1/namespaces/a/configmaps");
+        let pages: Vec<_> = cluster.list_paged("api/v1/pods", 10).map(uri).collect();
+        assert_eq!(pages, vec!["/api/v1/namespaces/default/pods?limit=10"]);
+    }
+
+    #[test]
+    fn watch_options_path_all_namespaces() {
+        let options = WatchOptions {
+            namespace: Some("default".to_owned()),
+            all_namespaces: true,
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"), "api/v1/pods?watch=true");
+    }
+
+    #[test]
+    fn events_all_namespaces() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            assert_eq!(request.uri.to_string(), "/api/v1/pods?watch=true");
+            response.send(br#"{"type": "ADDED", "object": {"metadata": {"namespace": "a"},
+                                                            "spec": {"x": 1, "y": 2}}}
+                              {"type": "ADDED", "object": {"metadata": {"namespace": "b"},
+                                                            "spec": {"x": 3, "y": 4}}}"#)
+                .unwrap();
+        });
+        let mut cluster = Cluster::new(&host).unwrap();
+        cluster.set_namespace("default");
+        let options = WatchOptions {
+            all_namespaces: true,
+            ..Default::default()
+        };
+        let namespaces: Vec<_> = cluster
+            .events_with_options::<WatchEvent<KubeObject<Point>>>("api/v1/pods", &options)
+            .unwrap()
+            .map(|event| match event.unwrap() {
+                WatchEvent::Added(object) => object.metadata.namespace.unwrap(),
+                event => panic!("unexpected event {:?}", event),
+            })
+            .collect();
+        assert_eq!(namespaces, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn watch_options_path_allow_watch_bookmarks() {
+        let options = WatchOptions {
+            allow_watch_bookmarks: true,
+            ..Default::default()
+        };
+        assert_eq!(options.path("api/v1/pods"),
+                   "api/v1/pods?watch=true&allowWatchBookmarks=true");
+    }
+
+    /// Serve requests by `handler` on a random local port and return host address of the server.
+    fn serve<H>(handler: H) -> String
+        where H: hyper::server::Handler + 'static
+    {
+        let server = hyper::server::Server::http("127.0.0.1:0").unwrap();
+        let mut listening = server.handle(handler).unwrap();
+        // Detach the server thread, otherwise dropping `Listening` would block on joining it.
+        listening.close().unwrap();
+        format!("http://{}", listening.socket)
+    }
+
+    #[test]
+    fn events_with_response() {
+        use hyper::header::ContentType;
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, mut response: Response| {
+            response.headers_mut().set(ContentType::json());
+            response.send(br#"{"x": 1, "y": 2}"#).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let (response, events) = cluster
+            .events_with_response::<Point>("points", &WatchOptions::default())
+            .unwrap();
+        assert_eq!(response.status, StatusCode::Ok);
+        assert_eq!(response.headers.get(), Some(&ContentType::json()));
+        assert_eq!(events.recv().unwrap().unwrap(), Point { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn cluster_list() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            assert_eq!(request.uri.to_string(), "/points");
+            response.send(br#"[{"x": 1, "y": 2}, {"x": 3, "y": 4}]"#).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let points = cluster.list::<Vec<Point>>("points").unwrap();
+        assert_eq!(points, vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cluster_keep_alive() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, response: Response| {
+            response.send(br#"[{"x": 1, "y": 2}]"#).unwrap();
+        });
+        let cluster = ClusterBuilder::new()
+            .host(&host)
+            .keep_alive(Duration::from_secs(30))
+            .build()
+            .unwrap();
+        let points = cluster.list::<Vec<Point>>("points").unwrap();
+        assert_eq!(points, vec![Point { x: 1, y: 2 }]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cluster_over_unix_socket() {
+        use std::io::Write;
+        use std::os::unix::net::UnixListener;
+        use std::{env, fs, process, thread};
+
+        let path = env::temp_dir().join(format!("kubewatch-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut byte = [0; 1];
+                while !request.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                    request.push(byte[0]);
+                }
+                assert!(request.starts_with(b"GET /points?watch=true HTTP/1.1\r\n"));
+                let body = r#"{"x": 1, "y": 2}"#;
+                write!(stream,
+                       "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                       body.len(),
+                       body)
+                    .unwrap();
+            }
+        });
+        let cluster = Cluster::over_unix_socket(&path).unwrap();
+        let events = cluster.events::<Point>("points").unwrap();
+        assert_eq!(events.recv().unwrap().unwrap(), Point { x: 1, y: 2 });
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cluster_list_paged() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            let page: &[u8] = match request.uri.to_string().as_str() {
+                "/points?limit=1" => br#"{"metadata": {"continue": "next"}, "items": [1]}"#,
+                "/points?limit=1&continue=next" => br#"{"metadata": {}, "items": [2]}"#,
+                uri => panic!("unexpected request {}", uri),
+            };
+            response.send(page).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let pages: Vec<_> = cluster.list_paged::<serde_json::Value>("points", 1)
+            .map(|page| page.unwrap()["items"][0].clone())
+            .collect();
+        assert_eq!(pages, vec![1, 2]);
+    }
+
+    #[test]
+    fn cluster_list_items() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, response: Response| {
+            let list = br#"{
+                "apiVersion": "v1",
+                "kind": "PointList",
+                "metadata": {"resourceVersion": "42"},
+                "items": [{"x": 1, "y": 2}, {"x": 3, "y": 4}]
+            }"#;
+            response.send(list).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let (version, items) = cluster.list_items::<Point>("points").unwrap();
+        assert_eq!(version, "42");
+        assert_eq!(items, vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+    }
+
+    #[test]
+    fn events_reconnecting_gives_up() {
+        use hyper::server::{Request, Response};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = AtomicUsize::new(0);
+        let host = serve(move |_: Request, mut response: Response| {
+            if requests.fetch_add(1, Ordering::SeqCst) > 0 {
+                *response.status_mut() = StatusCode::ServiceUnavailable;
+            }
+            response.send(br#"{"x": 1, "y": 2}"#).unwrap();
+        });
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counted = attempts.clone();
+        let policy = ReconnectPolicy {
+            max_retries: Some(2),
+            base_delay: Duration::from_millis(1),
+            on_reconnect: Some(Arc::new(move |attempt| {
+                counted.store(attempt as usize, Ordering::SeqCst);
+            })),
+            ..Default::default()
+        };
+        let cluster = ClusterBuilder::new().host(&host).reconnect_policy(policy).build().unwrap();
+        let watch = cluster.events_reconnecting::<Point>("points", &WatchOptions::default())
+            .unwrap();
+        let events: Vec<_> = watch.iter().collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(*events[0].as_ref().unwrap(), Point { x: 1, y: 2 });
+        let status = match events[2] {
+            Err(Error::UnexpectedStatus(status, _)) => status,
+            _ => panic!("unexpected event {:?}", events[2]),
+        };
+        assert_eq!(status, StatusCode::ServiceUnavailable);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert!(matches!(watch.status(), Some(WatchStatus::Errored(_))));
+    }
+
+    #[test]
+    fn watch_with_resync() {
+        use hyper::header::Connection;
+        use hyper::server::{Request, Response};
+        use std::sync::Mutex;
+
+        let uris = Arc::new(Mutex::new(Vec::new()));
+        let requests = uris.clone();
+        let host = serve(move |request: Request, mut response: Response| {
+            // Idle keep-alive connections of lists would block threads of the test server.
+            response.headers_mut().set(Connection::close());
+            let mut uris = requests.lock().unwrap();
+            uris.push(request.uri.to_string());
+            if uris.last().unwrap().contains("watch=true") {
+                response.send(br#"{"type": "ADDED", "object": {"x": 3, "y": 4}}"#).unwrap();
+            } else {
+                let list = format!(r#"{{"metadata": {{"resourceVersion": "{}"}},
+                                         "items": [{{"x": 1, "y": 2}}]}}"#,
+                                   uris.len());
+                response.send(list.as_bytes()).unwrap();
+            }
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let interval = Duration::from_secs(60);
+        let events: Vec<_> = cluster.watch_with_resync::<Point>("points", interval)
+            .unwrap()
+            .take(3)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events,
+                   vec![ResyncEvent::List(vec![Point { x: 1, y: 2 }]),
+                        ResyncEvent::Event(WatchEvent::Added(Point { x: 3, y: 4 })),
+                        ResyncEvent::List(vec![Point { x: 1, y: 2 }])]);
+        let uris = uris.lock().unwrap();
+        assert_eq!(uris[..3], ["/points", "/points?watch=true&resourceVersion=1", "/points"]);
+    }
+
+    #[test]
+    fn events_reconnecting() {
+        use hyper::server::{Request, Response};
+        use std::sync::Mutex;
+
+        let uris = Arc::new(Mutex::new(Vec::new()));
+        let handler_uris = uris.clone();
+        let host = serve(move |request: Request, response: Response| {
+            let mut uris = handler_uris.lock().unwrap();
+            uris.push(request.uri.to_string());
+            let frame = format!(r#"{{"object": {{"metadata": {{"resourceVersion": "{}"}}}}}}"#,
+                                uris.len());
+            response.send(frame.as_bytes()).unwrap();
+        });
+
+        let cluster = Cluster::new(&host).unwrap();
+        let events = cluster.events_reconnecting::<serde_json::Value>("pods",
+                                                                      &WatchOptions::default())
+            .unwrap();
+        let versions: Vec<_> = events.iter()
+            .take(2)
+            .map(|event| event.unwrap()["object"]["metadata"]["resourceVersion"].clone())
+            .collect();
+        assert_eq!(versions, vec!["1", "2"]);
+        assert_eq!(uris.lock().unwrap()[1], "/pods?watch=true&resourceVersion=1");
+        assert!(events.stats().reconnects >= 1);
+    }
+
+    #[test]
+    fn cluster_get_unexpected_status() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, mut response: Response| {
+            *response.status_mut() = StatusCode::Forbidden;
+            response.send(b"pods is forbidden").unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let response = cluster.get("api/v1/pods");
+        assert!(matches!(response,
+                         Err(Error::UnexpectedStatus(StatusCode::Forbidden, ref body))
+                             if body == "pods is forbidden"));
+    }
+
+    #[test]
+    fn cluster_get_api_error() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, mut response: Response| {
+            *response.status_mut() = StatusCode::NotFound;
+            response.send(br#"{"kind": "Status", "apiVersion": "v1", "status": "Failure",
+                               "message": "pods \"nginx\" not found", "reason": "NotFound"}"#)
+                .unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let status = match cluster.get("api/v1/namespaces/default/pods/nginx") {
+            Err(Error::ApiError(status)) => status,
+            response => panic!("unexpected response {:?}", response.map(|r| r.status)),
+        };
+        assert_eq!(status.reason, "NotFound");
+        assert_eq!(status.message, "pods \"nginx\" not found");
+        assert_eq!(status.code, 404);
+    }
+
+    #[test]
+    fn cluster_get_gone() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, mut response: Response| {
+            *response.status_mut() = StatusCode::Gone;
+            response.send(b"too old resource version").unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let response = cluster.get("api/v1/pods?watch=true&resourceVersion=1");
+        assert!(matches!(response, Err(Error::Expired)));
+    }
+
+    #[test]
+    fn cluster_get_rate_limited() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, mut response: Response| {
+            *response.status_mut() = StatusCode::TooManyRequests;
+            response.headers_mut().set_raw("Retry-After", vec![b"7".to_vec()]);
+            response.send(b"too many requests").unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let response = cluster.get("api/v1/pods");
+        assert!(matches!(response, Err(Error::RateLimited(after)) if after.as_secs() == 7));
+    }
+
+    #[test]
+    fn retry_after() {
+        let mut headers = Headers::new();
+        assert_eq!(super::retry_after(&headers), Duration::from_secs(1));
+        headers.set_raw("Retry-After", vec![b"Wed, 21 Oct 2015 07:28:00 GMT".to_vec()]);
+        assert_eq!(super::retry_after(&headers), Duration::from_secs(1));
+        headers.set_raw("Retry-After", vec![b" 120".to_vec()]);
+        assert_eq!(super::retry_after(&headers), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn cluster_get_headers() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            assert_eq!(request.headers.get_raw("X-Auth-Request-User"),
+                       Some(&[b"watcher".to_vec()][..]));
+            response.send(b"{}").unwrap();
+        });
+        let mut cluster = Cluster::new(&host).unwrap();
+        cluster.add_header("X-Auth-Request-User", "watcher");
+        assert!(cluster.get("api/v1/pods").is_ok());
+    }
+
+    #[test]
+    fn events_raw() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            assert_eq!(request.uri.to_string(), "/pods?watch=true");
+            response.send(br#"{"x": 1} {"x": 2}"#).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let mut stream = cluster.events_raw("pods", &WatchOptions::default()).unwrap();
+        let mut content = String::new();
+        stream.read_to_string(&mut content).unwrap();
+        assert_eq!(content, r#"{"x": 1} {"x": 2}"#);
+    }
+
+    #[test]
+    fn events_with() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, response: Response| {
+            response.send(br#"{"x": 1, "y": 2} {"x": 3, "y": 4}"#).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let (tx, rx) = mpsc::channel();
+        cluster.events_with("pods", move |event: Result<Point, Error>| {
+                tx.send(event.unwrap()).unwrap();
+            })
+            .unwrap();
+        assert_eq!(rx.iter().collect::<Vec<_>>(),
+                   vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+    }
+
+    #[test]
+    fn cluster_check() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, mut response: Response| {
+            match request.headers.get::<Authorization<Bearer>>().map(|auth| &auth.token[..]) {
+                _ if request.uri.to_string() != "/version" => {
+                    *response.status_mut() = StatusCode::NotFound
+                }
+                Some("admin") => (),
+                Some(_) => *response.status_mut() = StatusCode::Forbidden,
+                None => *response.status_mut() = StatusCode::Unauthorized,
+            }
+            response.send(b"{}").unwrap();
+        });
+        let mut cluster = Cluster::new(&host).unwrap();
+        assert!(matches!(cluster.check(), Err(Error::Unauthorized(_))));
+        cluster.set_token("user");
+        assert!(matches!(cluster.check(), Err(Error::Forbidden(_))));
+        cluster.set_token("admin");
+        assert!(cluster.check().is_ok());
+    }
+
+    #[test]
+    fn cluster_check_resolve_failed() {
+        let cluster = Cluster::new("http://does.not").unwrap();
+        assert!(matches!(cluster.check(), Err(Error::ResolveFailed(_))));
+    }
+
+    #[test]
+    fn cluster_basic_auth() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            let auth = request.headers.get_raw("Authorization").unwrap()[0].clone();
+            response.send(&auth).unwrap();
+        });
+        let mut cluster = Cluster::with_basic_auth(&host, "admin", "secret").unwrap();
+        let mut auth = String::new();
+        body(cluster.get("version").unwrap()).read_to_string(&mut auth).unwrap();
+        assert_eq!(auth, "Basic YWRtaW46c2VjcmV0");
+        cluster.set_token("token");
+        let mut auth = String::new();
+        body(cluster.get("version").unwrap()).read_to_string(&mut auth).unwrap();
+        assert_eq!(auth, "Bearer token");
+    }
+
+    /// Serve redirects of `/version` to `location`, the redirected request echoes its
+    /// `Authorization` header.
+    fn serve_redirect(location: Option<String>) -> String {
+        use hyper::header::Connection;
+        use hyper::server::{Request, Response};
+
+        serve(move |request: Request, mut response: Response| {
+            response.headers_mut().set(Connection::close());
+            if request.uri.to_string() == "/version" {
+                let location = location.clone().unwrap_or_else(|| "/redirected".to_owned());
+                *response.status_mut() = StatusCode::Found;
+                response.headers_mut().set(Location(location));
+                response.send(b"").unwrap();
+            } else {
+                // Echo credentials and impersonation headers which reached the redirect target.
+                let mut sent: Vec<String> = request.headers
+                    .iter()
+                    .filter(|header| {
+                        let name = header.name();
+                        name == "Authorization" || name.starts_with("Impersonate-")
+                    })
+                    .map(|header| format!("{}: {}", header.name(), header.value_string()))
+                    .collect();
+                sent.sort();
+                response.send(sent.join("\n").as_bytes()).unwrap();
+            }
+        })
+    }
+
+    /// Cluster authenticated by a token and impersonating a user with a group and an extra field.
+    fn impersonating_cluster(host: &str) -> Cluster {
+        let mut impersonation = Impersonation::user("jane");
+        impersonation.groups = vec!["admins".to_owned()];
+        impersonation.extra.insert("scopes".to_owned(), vec!["view".to_owned()]);
+        ClusterBuilder::new()
+            .host(host)
+            .token("token")
+            .impersonation(impersonation)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn cluster_redirect() {
+        let host = serve_redirect(None);
+        let cluster = impersonating_cluster(&host);
+        let mut sent = String::new();
+        body(cluster.get("version").unwrap()).read_to_string(&mut sent).unwrap();
+        assert_eq!(sent,
+                   "Authorization: Bearer token\nImpersonate-Extra-scopes: view\n\
+                    Impersonate-Group: admins\nImpersonate-User: jane");
+    }
+
+    #[test]
+    fn cluster_redirect_other_origin() {
+        let other = serve_redirect(None);
+        let host = serve_redirect(Some(format!("{}/redirected", other)));
+        let cluster = impersonating_cluster(&host);
+        let mut sent = String::new();
+        body(cluster.get("version").unwrap()).read_to_string(&mut sent).unwrap();
+        assert_eq!(sent, "");
+    }
+
+    #[test]
+    fn cluster_socks5_unreachable() {
+        let proxy = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let cluster = Cluster::with_socks5("http://10.0.0.1:8080", &proxy.to_string(), None)
+            .unwrap();
+        assert!(matches!(cluster.get("version"), Err(Error::ProxyFailed(_))));
+    }
+
+    #[test]
+    fn cluster_too_many_redirects() {
+        let host = serve_redirect(Some("/version".to_owned()));
+        let mut cluster = Cluster::new(&host).unwrap();
+        cluster.set_max_redirects(2);
+        assert!(matches!(cluster.get("version"), Err(Error::TooManyRedirects(_))));
+        cluster.set_max_redirects(0);
+        assert!(matches!(cluster.get("version"),
+                         Err(Error::UnexpectedStatus(StatusCode::Found, _))));
+    }
+
+    #[test]
+    fn cluster_post_object() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|mut request: Request, response: Response| {
+            assert_eq!(request.method, Method::Post);
+            assert_eq!(request.uri.to_string(), "/apis/example.com/v1/points");
+            assert_eq!(request.headers.get::<ContentType>(), Some(&ContentType::json()));
+            let mut content = String::new();
+            request.read_to_string(&mut content).unwrap();
+            assert_eq!(content, r#"{"x":1}"#);
+            response.send(br#"{"x": 1, "y": 2}"#).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let mut body = std::collections::BTreeMap::new();
+        body.insert("x", 1);
+        let created = cluster.post_object::<Point, _>("apis/example.com/v1/points", &body);
+        assert_eq!(created.unwrap(), Point { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn cluster_get_object() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, mut response: Response| {
+            if request.uri.to_string().ends_with("/clusterroles/a:b%2Fc") {
+                response.send(br#"{"x": 1, "y": 2}"#).unwrap();
+            } else {
+                *response.status_mut() = StatusCode::NotFound;
+                response.send(b"not found").unwrap();
+            }
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let name = "apis/rbac.authorization.k8s.io/v1/clusterroles";
+        assert_eq!(cluster.get_object::<Point>(name, "a:b/c").unwrap(), Point { x: 1, y: 2 });
+        assert!(matches!(cluster.get_object::<Point>(name, "d"),
+                         Err(Error::UnexpectedStatus(StatusCode::NotFound, _))));
+        assert!(matches!(cluster.get_object::<Point>(name, ".."),
+                         Err(Error::InvalidResource(_))));
+    }
+
+    #[test]
+    fn cluster_format_accept() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, mut response: Response| {
+            let expected: &[u8] = if request.uri.to_string().contains("watch=true") {
+                b"application/vnd.kubernetes.protobuf;stream=watch"
+            } else {
+                b"application/json"
+            };
+            if request.headers.get_raw("Accept") != Some(&[expected.to_vec()][..]) {
+                *response.status_mut() = StatusCode::NotAcceptable;
+            }
+            response.headers_mut().set_raw("Content-Type", vec![expected.to_vec()]);
+            response.send(b"{}").unwrap();
+        });
+        let cluster = ClusterBuilder::new().host(&host).format(Protobuf).build().unwrap();
+        assert!(cluster.events::<serde_json::Value>("api/v1/pods").is_ok());
+        assert!(cluster.list::<serde_json::Value>("api/v1/pods").is_ok());
+    }
+
+    #[test]
+    fn cluster_format_unexpected_content_type() {
+        use hyper::header::ContentType;
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, mut response: Response| {
+            response.headers_mut().set(ContentType::json());
+            response.send(br#"{"kind": "Status"}"#).unwrap();
+        });
+        let cluster = ClusterBuilder::new().host(&host).format(Protobuf).build().unwrap();
+        let events = cluster.events::<serde_json::Value>("api/v1/pods");
+        assert!(matches!(events,
+                         Err(Error::UnexpectedContentType(ref content_type))
+                             if content_type == "application/json"));
+    }
+
+    #[test]
+    fn cluster_partial_metadata() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            let accept = request.headers.get_raw("Accept").unwrap()[0].clone();
+            assert!(String::from_utf8(accept).unwrap().contains("as=PartialObjectMetadata"));
+            response.send(br#"{"type": "ADDED", "object": {"apiVersion": "meta.k8s.io/v1",
+                                "kind": "PartialObjectMetadata",
+                                "metadata": {"name": "nginx"}}}"#)
+                .unwrap();
+        });
+        let cluster = ClusterBuilder::new().host(&host).format(PartialMetadata).build().unwrap();
+        let events = cluster.events_typed::<PartialObjectMetadata>("api/v1/pods").unwrap();
+        match events.recv().unwrap().unwrap() {
+            WatchEvent::Added(pod) => assert_eq!(pod.metadata.name, "nginx"),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn cluster_impersonation() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, mut response: Response| {
+            let raw = |name: &str| {
+                request.headers
+                    .get_raw(name)
+                    .map(|values| values.iter().map(|v| String::from_utf8_lossy(v)).collect())
+            };
+            let expected = raw("Impersonate-User") == Some(vec!["jane".into()]) &&
+                           raw("Impersonate-Group") == Some(vec!["a".into(), "b".into()]) &&
+                           raw("Impersonate-Extra-example.com%2Fscopes") ==
+                           Some(vec!["view".into(), "edit".into()]);
+            if !expected {
+                *response.status_mut() = StatusCode::Forbidden;
+            }
+            response.send(b"[]").unwrap();
+        });
+        let mut impersonation = Impersonation::user("jane");
+        impersonation.groups = vec!["a".to_owned(), "b".to_owned()];
+        impersonation.extra
+            .insert("example.com/scopes".to_owned(), vec!["view".to_owned(), "edit".to_owned()]);
+        let cluster = ClusterBuilder::new()
+            .host(&host)
+            .impersonation(impersonation)
+            .build()
+            .unwrap();
+        assert!(cluster.list::<Vec<Point>>("points").is_ok());
+    }
+
+    #[test]
+    fn cluster_get_user_agent() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            let user_agent = request.headers.get::<UserAgent>().unwrap().to_string();
+            response.send(user_agent.as_bytes()).unwrap();
+        });
+        let mut cluster = Cluster::new(&host).unwrap();
+        let mut user_agent = String::new();
+        cluster.get("api/v1/pods").unwrap().read_to_string(&mut user_agent).unwrap();
+        assert_eq!(user_agent, USER_AGENT);
+
+        cluster.set_user_agent("my-watcher/1.0");
+        let mut user_agent = String::new();
+        cluster.get("api/v1/pods").unwrap().read_to_string(&mut user_agent).unwrap();
+        assert_eq!(user_agent, "my-watcher/1.0");
+    }
+
+    #[test]
+    fn events_gzip() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, mut response: Response| {
+            assert_eq!(request.headers.get(), Some(&AcceptEncoding(vec![qitem(Encoding::Gzip)])));
+            response.headers_mut().set(ContentEncoding(vec![Encoding::Gzip]));
+            response.send(&[0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xab,
+                            0x56, 0xaa, 0x50, 0xb2, 0x52, 0x30, 0xd4, 0x51, 0x50, 0xaa, 0x04,
+                            0xd2, 0x46, 0xb5, 0x00, 0x6b, 0xab, 0x40, 0x8a, 0x10, 0x00, 0x00,
+                            0x00])
+                .unwrap();
+        });
+        let mut cluster = Cluster::new(&host).unwrap();
+        cluster.set_gzip(true);
+        let events = cluster.events::<Point>("points").unwrap();
+        assert_eq!(events.recv().unwrap().unwrap(), Point { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn events_retry() {
+        use hyper::server::{Request, Response};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let handler_attempts = attempts.clone();
+        let host = serve(move |_: Request, mut response: Response| {
+            if handler_attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                *response.status_mut() = StatusCode::ServiceUnavailable;
+            }
+            response.send(br#"{"x": 1, "y": 2}"#).unwrap();
+        });
+        let cluster = ClusterBuilder::new()
+            .host(&host)
+            .retry(RetryPolicy {
+                max_attempts: 2,
+                base_delay: Duration::from_millis(1),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let events = cluster.events::<Point>("points").unwrap();
+        assert_eq!(events.recv().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn cluster_get_proxy() {
+        use hyper::server::{Request, Response};
+        use hyper::uri::RequestUri;
+
+        let proxy = serve(|request: Request, response: Response| {
+            let uri = match request.uri {
+                RequestUri::AbsoluteUri(uri) => uri.to_string(),
+                uri => panic!("request is not proxied: {}", uri),
+            };
+            response.send(uri.as_bytes()).unwrap();
+        });
+        let cluster = Cluster::with_proxy("http://kubernetes.invalid:8080", &proxy).unwrap();
+        let mut uri = String::new();
+        cluster.get("api/v1/pods").unwrap().read_to_string(&mut uri).unwrap();
+        assert_eq!(uri, "http://kubernetes.invalid:8080/api/v1/pods");
+    }
+
+    #[test]
+    fn cluster_with_client() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, response: Response| {
+            response.send(br#"{"x": 1, "y": 2}"#).unwrap();
+        });
+        let cluster = Cluster::with_client(&host, Client::new()).unwrap();
+        assert!(cluster.client.is_some());
+        let events = cluster.events::<Point>("points").unwrap();
+        assert_eq!(events.recv().unwrap().unwrap(), Point { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn cluster_list_reuses_connection() {
+        use hyper::server::{Request, Response};
+        use std::sync::Mutex;
+
+        let peers = Arc::new(Mutex::new(Vec::new()));
+        let handler_peers = peers.clone();
+        let host = serve(move |request: Request, response: Response| {
+            handler_peers.lock().unwrap().push(request.remote_addr);
+            response.send(b"[]").unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        cluster.list::<Vec<Point>>("points").unwrap();
+        cluster.list::<Vec<Point>>("points").unwrap();
+        let peers = peers.lock().unwrap();
+        assert_eq!(peers[0], peers[1]);
+    }
+
+    #[test]
+    fn cluster_get_timeout() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, response: Response| {
+            thread::sleep(Duration::from_millis(500));
+            response.send(b"{}").unwrap();
+        });
+        let mut cluster = Cluster::new(&host).unwrap();
+        cluster.set_read_timeout(Duration::from_millis(50));
+        let response = cluster.get("api/v1/pods");
+        assert!(matches!(response, Err(Error::ReadTimeout)));
+    }
+
+    #[test]
+    fn events_timeout() {
+        use hyper::server::{Request, Response};
+        use std::io::Write;
+
+        let host = serve(|_: Request, response: Response| {
+            let mut response = response.start().unwrap();
+            response.write_all(b"{\"x\": 1, \"y\": 2}").unwrap();
+            response.flush().unwrap();
+            thread::sleep(Duration::from_millis(500));
+        });
+        let mut cluster = Cluster::new(&host).unwrap();
+        cluster.set_read_timeout(Duration::from_millis(50));
+        let watch = cluster.events::<Point>("points").unwrap();
+        let mut events = watch.iter();
+        assert_eq!(events.next().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert!(matches!(events.next(), Some(Err(Error::ReadTimeout))));
+    }
+
+    #[test]
+    fn event_types() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|request: Request, response: Response| {
+            assert_eq!(request.uri.to_string(), "/points?watch=true");
+            response.send(br#"{"type": "ADDED", "object": {"x": 1, "y": 2}}
+                              {"type": "BOOKMARK", "object": {}}"#)
+                .unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let types: Vec<_> = cluster.event_types("points", &WatchOptions::default())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(types, vec!["ADDED", "BOOKMARK"]);
+    }
+
+    #[test]
+    fn events_max_duration() {
+        use hyper::server::{Request, Response};
+        use std::io::Write;
+
+        let host = serve(|_: Request, response: Response| {
+            let mut response = response.start().unwrap();
+            for _ in 0..50 {
+                if response.write_all(b"{\"x\": 1, \"y\": 2}").and_then(|_| response.flush())
+                    .is_err() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let options = WatchOptions {
+            max_duration: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let watch = cluster.events_with_options::<Point>("points", &options).unwrap();
+        assert!(watch.iter().all(|event| event.is_ok()));
+        assert!(start.elapsed() < Duration::from_millis(800));
+        assert!(!watch.is_closed());
+    }
+
+    #[test]
+    fn events_idle_timeout() {
+        use hyper::server::{Request, Response};
+        use std::io::Write;
+
+        let host = serve(|_: Request, response: Response| {
+            let mut response = response.start().unwrap();
+            response.write_all(b"{\"x\": 1, \"y\": 2}").unwrap();
+            response.flush().unwrap();
+            thread::sleep(Duration::from_millis(500));
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let options = WatchOptions {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let watch = cluster.events_with_options::<Point>("points", &options).unwrap();
+        let events: Vec<_> = watch.iter().collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(*events[0].as_ref().unwrap(), Point { x: 1, y: 2 });
+        assert!(matches!(events[1], Err(Error::IdleTimeout)));
+    }
+
+    #[test]
+    fn watch_reader() {
+        use hyper::server::{Request, Response};
+
+        let host = serve(|_: Request, response: Response| {
+            response.send(br#"{"x": 1, "y": 2}{"x": 3, "y": 4}"#).unwrap();
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let mut reader = cluster.watch_reader::<Point>("points", &WatchOptions::default()).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert_eq!(reader.next().unwrap().unwrap(), Point { x: 3, y: 4 });
+        assert!(reader.next().is_none());
+        assert!(reader.is_closed());
+        assert_eq!(reader.stats().events, 2);
+    }
+
+    #[test]
+    fn events_chunked() {
+        use hyper::server::{Request, Response};
+        use std::io::Write;
+
+        let host = serve(|_: Request, response: Response| {
+            // Every write is sent as a separate chunk, splitting objects, numbers and strings.
+            let chunks: &[&[u8]] = &[b"{\"x\"", b": 1", b"0, \"y\": 2}\n{", b"\"x\": 3, \"y",
+                                     b"\": 4", b"}"];
+            let mut response = response.start().unwrap();
+            for chunk in chunks {
+                response.write_all(chunk).unwrap();
+                response.flush().unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let events: Vec<_> = cluster.events::<Point>("points").unwrap().collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(*events[0].as_ref().unwrap(), Point { x: 10, y: 2 });
+        assert_eq!(*events[1].as_ref().unwrap(), Point { x: 3, y: 4 });
+    }
+
+    #[test]
+    fn events_cancel() {
+        use hyper::server::{Request, Response};
+        use std::io::Write;
+        use std::sync::mpsc::RecvTimeoutError;
+
+        let host = serve(|_: Request, response: Response| {
+            let mut response = response.start().unwrap();
+            response.write_all(b"{\"x\": 1, \"y\": 2}").unwrap();
+            response.flush().unwrap();
+            thread::sleep(Duration::from_secs(10));
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let cancel = CancelToken::new();
+        let options = WatchOptions {
+            cancel: Some(cancel.clone()),
+            ..Default::default()
+        };
+        let events = cluster.events_with_options::<Point>("points", &options).unwrap();
+        assert_eq!(events.recv().unwrap().unwrap(), Point { x: 1, y: 2 });
+        cancel.cancel();
+        assert!(cancel.is_cancelled());
+        let next = events.recv_timeout(Duration::from_secs(5));
+        assert!(matches!(next, Err(RecvTimeoutError::Disconnected)));
+    }
+
+    #[test]
+    fn events_stop() {
+        use hyper::server::{Request, Response};
+        use std::io::Write;
+        use std::sync::mpsc::RecvTimeoutError;
+
+        let host = serve(|_: Request, response: Response| {
+            let mut response = response.start().unwrap();
+            response.write_all(b"{\"x\": 1, \"y\": 2}").unwrap();
+            response.flush().unwrap();
+            thread::sleep(Duration::from_secs(10));
+        });
+        let cluster = Cluster::new(&host).unwrap();
+        let events = cluster.events::<Point>("points").unwrap();
+        assert_eq!(events.recv().unwrap().unwrap(), Point { x: 1, y: 2 });
+        events.stop();
+        let next = events.recv_timeout(Duration::from_secs(5));
+        assert!(matches!(next, Err(RecvTimeoutError::Disconnected)));
+        assert!(!events.is_closed());
+    }
+
+    impl Events for &'static str {
+        #[allow(unused_variables)] 
+        fn events_with_options<Event>(&self,
+                                      name: &str,
+                                      options: &WatchOptions)
+                                      -> Result<Watch<Event>, Error>
+            where Event: Deserialize + Send + 'static
+        {
+            let options = options.clone();
+            watch::spawn(name, self.as_bytes(), Interrupt::default(), options, None, Arc::new(Json))
+        }
+    }
+
+    #[derive(Deserialize, PartialEq, Eq, Debug)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn events_generator() {
+        let watch = r#"{"x": 1, "y": 2}{"x": 3, "y": 4}"#.events::<Point>("points").unwrap();
+        let mut events = watch.iter();
+        assert_eq!(events.next().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert_eq!(events.next().unwrap().unwrap(), Point { x: 3, y: 4 });
+    }
+
+    #[test]
+    fn events_malformed() {
+        let watch = r#"{"x": 1, "y": 2}{"x": "a"}garbage{"x": 3, "y": 4}"#
+            .events::<Point>("points")
+            .unwrap();
+        let mut events = watch.iter();
+        assert_eq!(events.next().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert!(matches!(events.next(),
+                         Some(Err(Error::InvalidEvent(_, ref frame))) if frame == r#"{"x": "a"}"#));
+        assert!(matches!(events.next(), Some(Err(Error::InvalidEvent(_, _)))));
+        assert_eq!(events.next().unwrap().unwrap(), Point { x: 3, y: 4 });
+    }
+
+    #[test]
+    fn events_skip_errors() {
+        let options = WatchOptions { skip_errors: true, ..Default::default() };
+        let watch = r#"{"x": 1, "y": 2}{"x": "a"}garbage{"x": 3, "y": 4}"#
+            .events_with_options::<Point>("points", &options)
+            .unwrap();
+        let events: Vec<_> = watch.iter().map(Result::unwrap).collect();
+        assert_eq!(events, vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+    }
+
+    #[test]
+    fn events_stream_io() {
+        let bytes = br#"{"x": 1, "y": 2}"#.to_vec().into_iter().map(Ok);
+        let reset = io::Error::new(io::ErrorKind::ConnectionReset, "connection reset");
+        let watch = "".generator::<Point, _>(bytes.chain(Some(Err(reset)))).unwrap();
+        let mut events = watch.iter();
+        assert_eq!(events.next().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert!(matches!(events.next(), Some(Err(Error::StreamIo(_)))));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn events_typed() {
+        let watch = r#"{"type": "ADDED", "object": {"x": 1, "y": 2}}
+                       {"type": "DELETED", "object": {"x": 1, "y": 2}}"#
+            .events_typed::<Point>("points")
+            .unwrap();
+        let mut events = watch.iter();
+        assert_eq!(events.next().unwrap().unwrap(), WatchEvent::Added(Point { x: 1, y: 2 }));
+        assert_eq!(events.next().unwrap().unwrap(), WatchEvent::Deleted(Point { x: 1, y: 2 }));
+    }
+
+    #[test]
+    fn events_expired() {
+        let watch = r#"{"x": 1, "y": 2}
+                       {"type": "ERROR", "object": {"kind": "Status", "code": 410}}
+                       {"x": 3, "y": 4}"#
+            .events::<Point>("points")
+            .unwrap();
+        let mut events = watch.iter();
+        assert_eq!(events.next().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert!(matches!(events.next(), Some(Err(Error::Expired))));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn events_multi() {
+        let watch = r#"{"x": 1, "y": 2}"#.events_multi::<Point>(&["a", "b"]).unwrap();
+        let mut events: Vec<_> = watch.iter().map(Result::unwrap).collect();
+        events.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(events,
+                   vec![("a".to_owned(), Point { x: 1, y: 2 }),
+                        ("b".to_owned(), Point { x: 1, y: 2 })]);
+    }
+}
@@ -0,0 +1,138 @@
+//! In-memory stand-in for `Cluster`, serving canned events to tests.
+
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+
+use watch;
+use {Error, Events, Watch, WatchOptions};
+
+/// Source of events serving a fixed sequence of JSON frames and errors, so handlers of events can
+/// be tested without an API server. Every watch started on it delivers the whole sequence in
+/// order and then ends, as if the server closed the stream. Watch options, e.g. `skip_errors`,
+/// apply to the frames the same way as to a real watch.
+///
+/// ```
+/// use kubewatch::{Error, Events, MockCluster, WatchEvent};
+///
+/// let cluster = MockCluster::new()
+///     .frame(r#"{"type": "ADDED", "object": {"metadata": {"name": "nginx"}}}"#)
+///     .error(|| Error::ReadTimeout)
+///     .frame(r#"{"type": "DELETED", "object": {"metadata": {"name": "nginx"}}}"#);
+/// let watch = cluster.events_typed::<serde_json::Value>("api/v1/pods").unwrap();
+/// let events: Vec<_> = watch.collect();
+/// assert!(matches!(events[0], Ok(WatchEvent::Added(_))));
+/// assert!(matches!(events[1], Err(Error::ReadTimeout)));
+/// assert!(matches!(events[2], Ok(WatchEvent::Deleted(_))));
+/// ```
+#[derive(Clone, Default)]
+pub struct MockCluster {
+    script: Vec<MockItem>,
+}
+
+/// Single item served by `MockCluster`.
+#[derive(Clone)]
+enum MockItem {
+    Frames(Vec<u8>),
+    Error(Arc<dyn Fn() -> Error + Send + Sync>),
+}
+
+impl MockCluster {
+    /// Initialize mock serving no events.
+    pub fn new() -> MockCluster {
+        MockCluster::default()
+    }
+
+    /// Serve JSON `frames`, e.g. a single watch event. Multiple frames may be concatenated.
+    pub fn frame(mut self, frames: &str) -> MockCluster {
+        self.script.push(MockItem::Frames(frames.as_bytes().to_owned()));
+        self
+    }
+
+    /// Serve an error created by `error`, which is called for every started watch.
+    pub fn error<MakeError>(mut self, error: MakeError) -> MockCluster
+        where MakeError: Fn() -> Error + Send + Sync + 'static
+    {
+        self.script.push(MockItem::Error(Arc::new(error)));
+        self
+    }
+}
+
+impl fmt::Debug for MockCluster {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let script: Vec<_> = self.script
+            .iter()
+            .map(|item| match *item {
+                MockItem::Frames(ref frames) => String::from_utf8_lossy(frames).into_owned(),
+                MockItem::Error(ref error) => error().to_string(),
+            })
+            .collect();
+        f.debug_struct("MockCluster").field("script", &script).finish()
+    }
+}
+
+impl Events for MockCluster {
+    fn events_with_options<Event>(&self,
+                                  name: &str,
+                                  options: &WatchOptions)
+                                  -> Result<Watch<Event>, Error>
+        where Event: Deserialize + Send + 'static
+    {
+        let script = self.script
+            .iter()
+            .map(|item| match *item {
+                MockItem::Frames(ref frames) => Ok(frames.clone()),
+                MockItem::Error(ref error) => Err(error()),
+            })
+            .collect();
+        watch::spawn_script(name, script, options.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, PartialEq, Eq, Debug)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn mock_cluster() {
+        let cluster = MockCluster::new()
+            .frame(r#"{"x": 1, "y": 2}{"x": 3, "y": 4}"#)
+            .error(|| Error::ReadTimeout)
+            .frame(r#"{"x": 5, "y": 6}"#);
+        let watch = cluster.events::<Point>("points").unwrap();
+        let events: Vec<_> = watch.iter().collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].as_ref().unwrap(), &Point { x: 1, y: 2 });
+        assert_eq!(events[1].as_ref().unwrap(), &Point { x: 3, y: 4 });
+        assert!(matches!(events[2], Err(Error::ReadTimeout)));
+        assert_eq!(events[3].as_ref().unwrap(), &Point { x: 5, y: 6 });
+        assert!(watch.is_closed());
+    }
+
+    #[test]
+    fn mock_cluster_repeated() {
+        let cluster = MockCluster::new().error(|| Error::Expired);
+        for _ in 0..2 {
+            let mut events = cluster.events::<Point>("points").unwrap();
+            assert!(matches!(events.next(), Some(Err(Error::Expired))));
+            assert!(events.next().is_none());
+        }
+    }
+
+    #[test]
+    fn mock_cluster_skip_errors() {
+        let cluster = MockCluster::new().frame(r#"{"x": "a"}{"x": 1, "y": 2}"#);
+        let options = WatchOptions { skip_errors: true, ..Default::default() };
+        let events: Vec<_> = cluster.events_with_options::<Point>("points", &options)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events, vec![Point { x: 1, y: 2 }]);
+    }
+}
@@ -0,0 +1,131 @@
+//! Typed representation of fields shared by all Kubernetes objects.
+
+use std::collections::BTreeMap;
+
+/// Metadata identifying an object of any kind.
+#[derive(Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct ObjectMeta {
+    /// Name of the object, unique within its namespace.
+    pub name: String,
+    /// Namespace of the object, not set for cluster-scoped objects.
+    pub namespace: Option<String>,
+    /// Unique identifier of the object, which is not reused by recreated objects of the same name.
+    pub uid: String,
+    /// Version of the object, changed with every modification.
+    #[serde(rename = "resourceVersion")]
+    pub resource_version: String,
+    /// Labels attached to the object.
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Object of any kind with given `Spec`, so only the specification needs to be defined for every
+/// watched resource.
+///
+/// ```no_run
+/// #[macro_use]
+/// extern crate serde_derive;
+/// extern crate kubewatch;
+///
+/// use kubewatch::{Events, KubeObject, WatchEvent};
+///
+/// #[derive(Deserialize, Debug)]
+/// struct ServiceSpec {
+///     #[serde(rename = "clusterIP")]
+///     cluster_ip: String,
+/// }
+///
+/// fn main() {
+///     let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+///     let events = cluster
+///         .events::<WatchEvent<KubeObject<ServiceSpec>>>("api/v1/services")
+///         .unwrap();
+///     for event in events {
+///         if let Ok(WatchEvent::Added(service)) = event {
+///             println!("{} {}", service.metadata.name, service.spec.cluster_ip);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct KubeObject<Spec> {
+    /// API group and version of the object (e.g. `apps/v1`).
+    #[serde(rename = "apiVersion", default)]
+    pub api_version: String,
+    /// Kind of the object (e.g. `Deployment`).
+    #[serde(default)]
+    pub kind: String,
+    /// Metadata of the object.
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    /// Desired state of the object.
+    pub spec: Spec,
+}
+
+/// Object of any kind reduced to its metadata, as sent by the server when requested by the
+/// `PartialMetadata` format. It suits watches which only key off names or labels of objects.
+#[derive(Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct PartialObjectMetadata {
+    /// API group and version of the object, `meta.k8s.io/v1` if reduced by the server.
+    #[serde(rename = "apiVersion", default)]
+    pub api_version: String,
+    /// Kind of the object, `PartialObjectMetadata` if reduced by the server.
+    #[serde(default)]
+    pub kind: String,
+    /// Metadata of the object.
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{self, Value};
+    use WatchEvent;
+
+    #[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+    struct PodSpec {
+        #[serde(rename = "nodeName")]
+        node_name: String,
+    }
+
+    #[test]
+    fn kube_object() {
+        let pod = r#"{"apiVersion": "v1", "kind": "Pod",
+                      "metadata": {"name": "nginx", "namespace": "default", "uid": "1-2",
+                                   "resourceVersion": "42", "labels": {"app": "nginx"}},
+                      "spec": {"nodeName": "n1"}, "status": {"phase": "Running"}}"#;
+        let pod: KubeObject<PodSpec> = serde_json::from_str(pod).unwrap();
+        assert_eq!(pod.kind, "Pod");
+        assert_eq!(pod.metadata.name, "nginx");
+        assert_eq!(pod.metadata.namespace, Some("default".to_owned()));
+        assert_eq!(pod.metadata.uid, "1-2");
+        assert_eq!(pod.metadata.resource_version, "42");
+        assert_eq!(pod.metadata.labels["app"], "nginx");
+        assert_eq!(pod.spec, PodSpec { node_name: "n1".to_owned() });
+    }
+
+    #[test]
+    fn kube_object_watch_event() {
+        let event = r#"{"type": "DELETED",
+                        "object": {"metadata": {"name": "admin"}, "spec": {}}}"#;
+        let event: WatchEvent<KubeObject<Value>> = serde_json::from_str(event).unwrap();
+        let object = match event {
+            WatchEvent::Deleted(object) => object,
+            event => panic!("unexpected event {:?}", event),
+        };
+        assert_eq!(object.metadata.name, "admin");
+        assert_eq!(object.metadata.namespace, None);
+        assert!(object.metadata.labels.is_empty());
+    }
+
+    #[test]
+    fn partial_object_metadata() {
+        let pod = r#"{"apiVersion": "v1", "kind": "Pod",
+                      "metadata": {"name": "nginx", "labels": {"app": "nginx"}},
+                      "spec": {"nodeName": "n1"}}"#;
+        let pod: PartialObjectMetadata = serde_json::from_str(pod).unwrap();
+        assert_eq!(pod.kind, "Pod");
+        assert_eq!(pod.metadata.labels["app"], "nginx");
+    }
+}
@@ -0,0 +1,86 @@
+//! Iterator over a list of resources read in chunks.
+
+use serde::Deserialize;
+use serde_json::{self, Value};
+use std::marker::PhantomData;
+use url::form_urlencoded;
+
+use {validate_name, Cluster, Error};
+
+/// Iterator over pages of resources returned by `Cluster::list_paged`. Every page is requested
+/// only once the previous one was consumed, following its `metadata.continue` token.
+#[derive(Debug)]
+pub struct Pages<List> {
+    cluster: Cluster,
+    name: String,
+    limit: u32,
+    token: Option<String>,
+    done: bool,
+    list: PhantomData<List>,
+}
+
+impl<List> Pages<List> {
+    pub(crate) fn new(cluster: Cluster, name: &str, limit: u32) -> Pages<List> {
+        Pages {
+            cluster,
+            name: name.to_owned(),
+            limit,
+            token: None,
+            done: false,
+            list: PhantomData,
+        }
+    }
+
+    /// Build path of the next page.
+    fn path(&self) -> String {
+        let mut query = form_urlencoded::Serializer::new(String::new());
+        query.append_pair("limit", &self.limit.to_string());
+        if let Some(ref token) = self.token {
+            query.append_pair("continue", token);
+        }
+        format!("{}?{}", self.name, query.finish())
+    }
+}
+
+impl<List> Iterator for Pages<List>
+    where List: Deserialize
+{
+    type Item = Result<List, Error>;
+
+    fn next(&mut self) -> Option<Result<List, Error>> {
+        if self.done {
+            return None;
+        }
+        if let Err(e) = validate_name(&self.name) {
+            self.done = true;
+            return Some(Err(e));
+        }
+        let page = match self.cluster.get_json::<Value>(&self.path()) {
+            Ok(page) => page,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        self.token = page.pointer("/metadata/continue")
+            .and_then(Value::as_str)
+            .filter(|token| !token.is_empty())
+            .map(str::to_owned);
+        self.done = self.token.is_none();
+        Some(serde_json::from_value(page).map_err(Error::DeserializationFailed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path() {
+        let cluster = Cluster::new("http://127.0.0.1:8080").unwrap();
+        let mut pages = Pages::<Value>::new(cluster, "api/v1/pods", 100);
+        assert_eq!(pages.path(), "api/v1/pods?limit=100");
+        pages.token = Some("a+b".to_owned());
+        assert_eq!(pages.path(), "api/v1/pods?limit=100&continue=a%2Bb");
+    }
+}
@@ -0,0 +1,161 @@
+//! Descriptor of Kubernetes resources, used to build their API paths.
+
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+use Error;
+
+/// Kubernetes resource identified by its API group, version and name, optionally limited to a
+/// namespace. Its `path` can be passed as `name` of watched or listed resources. Custom resources
+/// are described the same way, by the group, version and plural name of their definition.
+///
+/// ```no_run
+/// use kubewatch::{Events, Resource};
+///
+/// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+/// let deployments = Resource::group("apps", "v1", "deployments").namespace("default");
+/// let events = cluster.events::<serde_json::Value>(&deployments.path()).unwrap();
+/// let widgets = Resource::api_version("example.com/v1", "widgets");
+/// let events = cluster.events::<serde_json::Value>(&widgets.path()).unwrap();
+/// let status = Resource::core("v1", "pods")
+///     .namespace("default")
+///     .object("nginx")
+///     .subresource("status");
+/// let pod = cluster.list::<serde_json::Value>(&status.try_path().unwrap()).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    /// API group, `None` for the core group (e.g. pods or services).
+    pub group: Option<String>,
+    /// Version of the API (e.g. `v1`).
+    pub version: String,
+    /// Plural name of the resource (e.g. `pods`).
+    pub name: String,
+    /// Namespace of the resources, if not set, resources from all namespaces are used.
+    pub namespace: Option<String>,
+    /// Name of a single object of the resource, if not set, all objects are used.
+    pub object: Option<String>,
+    /// Sub-resource of the object (e.g. `status` or `scale`), requires `object` to be set.
+    pub subresource: Option<String>,
+}
+
+impl Resource {
+    /// Describe resource of the core API group, served under `/api`.
+    pub fn core(version: &str, name: &str) -> Resource {
+        Resource {
+            group: None,
+            version: version.to_owned(),
+            name: name.to_owned(),
+            namespace: None,
+            object: None,
+            subresource: None,
+        }
+    }
+
+    /// Describe resource of a named API group, served under `/apis`.
+    pub fn group(group: &str, version: &str, name: &str) -> Resource {
+        Resource { group: Some(group.to_owned()), ..Resource::core(version, name) }
+    }
+
+    /// Describe resource by `apiVersion` of its objects (e.g. `v1` or `example.com/v1`), as
+    /// written in manifests and custom resource definitions.
+    pub fn api_version(api_version: &str, name: &str) -> Resource {
+        match api_version.rfind('/') {
+            Some(i) => Resource::group(&api_version[..i], &api_version[i + 1..], name),
+            None => Resource::core(api_version, name),
+        }
+    }
+
+    /// Limit the resource to given namespace.
+    pub fn namespace(mut self, namespace: &str) -> Resource {
+        self.namespace = Some(namespace.to_owned());
+        self
+    }
+
+    /// Limit the resource to a single object with given name.
+    pub fn object(mut self, name: &str) -> Resource {
+        self.object = Some(name.to_owned());
+        self
+    }
+
+    /// Use given sub-resource of the object (e.g. `status`), the object must be named too.
+    pub fn subresource(mut self, subresource: &str) -> Resource {
+        self.subresource = Some(subresource.to_owned());
+        self
+    }
+
+    /// Build API path of the resource (e.g. `apis/apps/v1/namespaces/default/deployments`). The
+    /// sub-resource is left out unless an object is named, see `try_path`.
+    pub fn path(&self) -> String {
+        let mut path = match self.group {
+            Some(ref group) => format!("apis/{}/{}/", group, self.version),
+            None => format!("api/{}/", self.version),
+        };
+        if let Some(ref namespace) = self.namespace {
+            let namespace = utf8_percent_encode(namespace, PATH_SEGMENT_ENCODE_SET);
+            path.push_str(&format!("namespaces/{}/", namespace));
+        }
+        path.push_str(&self.name);
+        if let Some(ref object) = self.object {
+            let object = utf8_percent_encode(object, PATH_SEGMENT_ENCODE_SET);
+            path.push_str(&format!("/{}", object));
+            if let Some(ref subresource) = self.subresource {
+                path.push_str(&format!("/{}", subresource));
+            }
+        }
+        path
+    }
+
+    /// Build API path of the resource like `path`, failing with `Error::InvalidResource` if a
+    /// sub-resource is set without naming an object.
+    pub fn try_path(&self) -> Result<String, Error> {
+        if self.subresource.is_some() && self.object.is_none() {
+            return Err(Error::InvalidResource(self.path()));
+        }
+        Ok(self.path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core() {
+        assert_eq!(Resource::core("v1", "pods").path(), "api/v1/pods");
+    }
+
+    #[test]
+    fn group() {
+        assert_eq!(Resource::group("apps", "v1", "deployments").path(),
+                   "apis/apps/v1/deployments");
+    }
+
+    #[test]
+    fn api_version() {
+        assert_eq!(Resource::api_version("v1", "pods"), Resource::core("v1", "pods"));
+        assert_eq!(Resource::api_version("example.com/v1alpha1", "widgets").path(),
+                   "apis/example.com/v1alpha1/widgets");
+    }
+
+    #[test]
+    fn namespaced() {
+        let resource = Resource::group("apps", "v1", "deployments").namespace("kube system");
+        assert_eq!(resource.path(), "apis/apps/v1/namespaces/kube%20system/deployments");
+    }
+
+    #[test]
+    fn subresource() {
+        let resource = Resource::core("v1", "pods")
+            .namespace("default")
+            .object("nginx")
+            .subresource("status");
+        assert_eq!(resource.try_path().unwrap(), "api/v1/namespaces/default/pods/nginx/status");
+    }
+
+    #[test]
+    fn subresource_without_object() {
+        let resource = Resource::group("apps", "v1", "deployments").subresource("scale");
+        assert_eq!(resource.path(), "apis/apps/v1/deployments");
+        assert!(matches!(resource.try_path(), Err(Error::InvalidResource(_))));
+    }
+}
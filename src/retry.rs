@@ -0,0 +1,316 @@
+//! Retrying of transient failures while establishing a watch.
+
+use hyper;
+use std::cmp;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use Error;
+
+/// Policy of retrying failed attempts to establish a watch. Only connection failures, timeouts,
+/// 5xx statuses and rate limiting are retried, other errors are returned immediately. Rate
+/// limited attempts are retried after the time requested by the server.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use kubewatch::RetryPolicy;
+///
+/// let cluster = kubewatch::ClusterBuilder::new()
+///     .host("http://127.0.0.1:8080")
+///     .retry(RetryPolicy { max_attempts: 5, ..Default::default() })
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximal number of attempts, including the first one. `1` disables retrying.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after every failed attempt.
+    pub base_delay: Duration,
+    /// Upper bound of the delay between attempts.
+    pub max_delay: Duration,
+    /// Fraction of the delay (between `0.0` and `1.0`) which is randomly subtracted from it, so
+    /// many clients do not retry at the same time.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    /// Policy which does not retry at all.
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run `attempt` until it succeeds, fails with an error which is not transient or the
+    /// maximal number of attempts is reached.
+    pub(crate) fn run<T, Attempt>(&self, mut attempt: Attempt) -> Result<T, Error>
+        where Attempt: FnMut() -> Result<T, Error>
+    {
+        let mut delay = self.base_delay;
+        let mut attempts = 1;
+        loop {
+            match attempt() {
+                Err(ref e) if attempts < self.max_attempts && is_transient(e) => {
+                    match *e {
+                        Error::RateLimited(after) => thread::sleep(after),
+                        _ => thread::sleep(self.jittered(delay)),
+                    }
+                    delay = cmp::min(delay * 2, self.max_delay);
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn jittered(&self, delay: Duration) -> Duration {
+        jittered(delay, self.jitter)
+    }
+}
+
+/// Randomly subtract up to `jitter` fraction (between `0.0` and `1.0`) from `delay`.
+fn jittered(delay: Duration, jitter: f64) -> Duration {
+    let jitter = jitter.clamp(0.0, 1.0);
+    if jitter == 0.0 {
+        return delay;
+    }
+    let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+    delay.mul_f64(1.0 - jitter * random)
+}
+
+/// Fraction of `ReconnectPolicy::base_delay` randomly subtracted from it while a watch waits for
+/// a reconnect slot, so waiting watches do not retry in lockstep.
+const SLOT_JITTER: f64 = 0.5;
+
+/// Policy of re-establishing watches of `Cluster::events_reconnecting` once they are closed.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use kubewatch::ReconnectPolicy;
+///
+/// let cluster = kubewatch::ClusterBuilder::new()
+///     .host("http://127.0.0.1:8080")
+///     .reconnect_policy(ReconnectPolicy {
+///         max_retries: Some(10),
+///         on_reconnect: Some(Arc::new(|attempt| println!("reconnecting, attempt {}", attempt))),
+///         ..Default::default()
+///     })
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone)]
+pub struct ReconnectPolicy {
+    /// Maximal number of consecutive failed reconnect attempts, after which the watch ends with
+    /// the error of the last one. If not set, reconnecting continues until the watch is stopped.
+    pub max_retries: Option<u32>,
+    /// Delay before the first reconnect attempt, doubled after every failed attempt.
+    pub base_delay: Duration,
+    /// Upper bound of the delay between reconnect attempts.
+    pub max_delay: Duration,
+    /// Called before every reconnect attempt with its number, starting from `1` after every
+    /// closed watch, e.g. to log it.
+    pub on_reconnect: Option<Arc<dyn Fn(u32) + Send + Sync>>,
+    /// Maximal number of watches of the `Cluster` and its clones re-establishing their
+    /// connections at the same time, so a restarted API server is not hit by all of them at
+    /// once. Watches beyond the limit wait for their turn, retrying after a jittered
+    /// `base_delay`. If not set, reconnecting is not limited.
+    pub max_concurrent: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    /// Policy reconnecting until the watch is stopped.
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_retries: None,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            on_reconnect: None,
+            max_concurrent: None,
+        }
+    }
+}
+
+impl fmt::Debug for ReconnectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReconnectPolicy")
+            .field("max_retries", &self.max_retries)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("on_reconnect", &self.on_reconnect.as_ref().map(|_| "Fn(u32)"))
+            .field("max_concurrent", &self.max_concurrent)
+            .finish()
+    }
+}
+
+impl ReconnectPolicy {
+    /// Wait until one of `slots` is free and take it, polling it after a jittered `base_delay`.
+    /// `None` is returned if the watch is `stopped` in the meantime.
+    pub(crate) fn wait_for_slot<'a, Stopped>(&self,
+                                             slots: &'a ReconnectSlots,
+                                             stopped: Stopped)
+                                             -> Option<ReconnectSlot<'a>>
+        where Stopped: Fn() -> bool
+    {
+        loop {
+            if let Some(slot) = slots.try_take(self.max_concurrent) {
+                return Some(slot);
+            }
+            thread::sleep(jittered(self.base_delay, SLOT_JITTER));
+            if stopped() {
+                return None;
+            }
+        }
+    }
+}
+
+/// Counter of watches re-establishing their connections, shared by a `Cluster` and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct ReconnectSlots {
+    taken: Mutex<usize>,
+}
+
+impl ReconnectSlots {
+    /// Take a slot unless `max` slots are taken already.
+    fn try_take(&self, max: Option<usize>) -> Option<ReconnectSlot<'_>> {
+        let mut taken = self.taken.lock().unwrap();
+        if max.is_some_and(|max| *taken >= max) {
+            return None;
+        }
+        *taken += 1;
+        Some(ReconnectSlot { slots: self })
+    }
+}
+
+/// Slot taken from `ReconnectSlots`, freed once dropped.
+pub(crate) struct ReconnectSlot<'a> {
+    slots: &'a ReconnectSlots,
+}
+
+impl<'a> Drop for ReconnectSlot<'a> {
+    fn drop(&mut self) {
+        *self.slots.taken.lock().unwrap() -= 1;
+    }
+}
+
+/// Check whether `error` may disappear when the request is repeated.
+fn is_transient(error: &Error) -> bool {
+    match *error {
+        Error::HttpRequestFailed(hyper::Error::Io(_), _) |
+        Error::ConnectTimeout |
+        Error::ReadTimeout |
+        Error::RateLimited(_) => true,
+        Error::UnexpectedStatus(status, _) => status.is_server_error(),
+        Error::ApiError(ref status) => status.code >= 500,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::status::StatusCode;
+    use Status;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn run_retries_transient() {
+        let mut attempts = 0;
+        let result = policy().run(|| {
+            attempts += 1;
+            match attempts {
+                1 => Err(Error::ReadTimeout),
+                2 => Err(Error::UnexpectedStatus(StatusCode::ServiceUnavailable, String::new())),
+                _ => Ok(attempts),
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn run_gives_up() {
+        let mut attempts = 0;
+        let result = policy().run(|| -> Result<(), Error> {
+            attempts += 1;
+            Err(Error::ReadTimeout)
+        });
+        assert!(matches!(result, Err(Error::ReadTimeout)));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn run_rate_limited() {
+        let mut attempts = 0;
+        let result = policy().run(|| {
+            attempts += 1;
+            match attempts {
+                1 => Err(Error::RateLimited(Duration::from_millis(1))),
+                _ => Ok(attempts),
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    fn run_client_error() {
+        let mut attempts = 0;
+        let result = policy().run(|| -> Result<(), Error> {
+            attempts += 1;
+            Err(Error::UnexpectedStatus(StatusCode::Forbidden, String::new()))
+        });
+        assert!(matches!(result, Err(Error::UnexpectedStatus(StatusCode::Forbidden, _))));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn is_transient_api_error() {
+        assert!(is_transient(&Error::ApiError(Status { code: 500, ..Default::default() })));
+        assert!(!is_transient(&Error::ApiError(Status { code: 404, ..Default::default() })));
+    }
+
+    #[test]
+    fn reconnect_slots() {
+        let slots = ReconnectSlots::default();
+        let first = slots.try_take(Some(2)).unwrap();
+        let _second = slots.try_take(Some(2)).unwrap();
+        assert!(slots.try_take(Some(2)).is_none());
+        assert!(slots.try_take(None).is_some());
+        drop(first);
+        assert!(slots.try_take(Some(2)).is_some());
+    }
+
+    #[test]
+    fn wait_for_slot_stopped() {
+        let slots = ReconnectSlots::default();
+        let policy = ReconnectPolicy {
+            base_delay: Duration::from_millis(1),
+            max_concurrent: Some(1),
+            ..Default::default()
+        };
+        let _slot = policy.wait_for_slot(&slots, || false).unwrap();
+        assert!(policy.wait_for_slot(&slots, || true).is_none());
+    }
+
+    #[test]
+    fn jittered() {
+        let policy = RetryPolicy { jitter: 0.5, ..Default::default() };
+        let delay = policy.jittered(Duration::from_secs(2));
+        assert!(delay >= Duration::from_secs(1) && delay <= Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,234 @@
+//! Tunneling of connections to the API server through a SOCKS5 proxy (RFC 1928).
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, TcpStream};
+use std::time::Duration;
+
+use connect;
+
+/// SOCKS5 proxy at `address` (`host:port`), authenticating by username and password if set.
+#[derive(Clone)]
+pub struct Socks5 {
+    pub address: String,
+    pub auth: Option<(String, String)>,
+}
+
+impl fmt::Debug for Socks5 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Socks5")
+            .field("address", &self.address)
+            .field("user", &self.auth.as_ref().map(|auth| &auth.0))
+            .finish()
+    }
+}
+
+impl Socks5 {
+    /// Connect to `host` and `port` through the proxy, connecting to the proxy within `timeout`
+    /// if set. Failures of the proxy itself are recognized by `is_proxy_failure`, while failures
+    /// of the proxy to reach the target are reported as plain I/O errors.
+    pub fn connect(&self,
+                   host: &str,
+                   port: u16,
+                   timeout: Option<Duration>)
+                   -> io::Result<TcpStream> {
+        let mut socket = self.connect_proxy(timeout).map_err(proxy_failure)?;
+        self.handshake(&mut socket, host, port)?;
+        Ok(socket)
+    }
+
+    fn connect_proxy(&self, timeout: Option<Duration>) -> io::Result<TcpStream> {
+        let i = self.address
+            .rfind(':')
+            .ok_or_else(|| invalid("SOCKS5 proxy address has no port"))?;
+        let port = self.address[i + 1..]
+            .parse()
+            .map_err(|_| invalid("invalid SOCKS5 proxy port"))?;
+        let host = self.address[..i].trim_start_matches('[').trim_end_matches(']');
+        connect(host, port, timeout)
+    }
+
+    fn handshake<S>(&self, socket: &mut S, host: &str, port: u16) -> io::Result<()>
+        where S: Read + Write
+    {
+        // Offer no authentication, and username and password authentication if configured.
+        let methods: &[u8] = if self.auth.is_some() { &[5, 2, 0, 2] } else { &[5, 1, 0] };
+        socket.write_all(methods).map_err(proxy_failure)?;
+        let mut reply = [0; 2];
+        socket.read_exact(&mut reply).map_err(proxy_failure)?;
+        match (reply, &self.auth) {
+            ([5, 0], _) => (),
+            ([5, 2], &Some((ref user, ref password))) => {
+                if user.len() > 255 || password.len() > 255 {
+                    return Err(proxy_failure(invalid("SOCKS5 credentials are too long")));
+                }
+                let mut request = vec![1, user.len() as u8];
+                request.extend_from_slice(user.as_bytes());
+                request.push(password.len() as u8);
+                request.extend_from_slice(password.as_bytes());
+                socket.write_all(&request).map_err(proxy_failure)?;
+                socket.read_exact(&mut reply).map_err(proxy_failure)?;
+                if reply[1] != 0 {
+                    return Err(proxy_failure(denied("SOCKS5 proxy rejected credentials")));
+                }
+            }
+            _ => {
+                let message = "SOCKS5 proxy accepts no offered authentication";
+                return Err(proxy_failure(denied(message)));
+            }
+        }
+
+        let mut request = vec![5, 1, 0];
+        match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(IpAddr::V4(ip)) => {
+                request.push(1);
+                request.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                request.push(4);
+                request.extend_from_slice(&ip.octets());
+            }
+            Err(_) if host.len() <= 255 => {
+                request.extend_from_slice(&[3, host.len() as u8]);
+                request.extend_from_slice(host.as_bytes());
+            }
+            Err(_) => return Err(invalid("host name is too long for SOCKS5")),
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        socket.write_all(&request).map_err(proxy_failure)?;
+        let mut reply = [0; 4];
+        socket.read_exact(&mut reply).map_err(proxy_failure)?;
+        let address_length = match reply[3] {
+            1 => 4,
+            4 => 16,
+            3 => {
+                let mut length = [0];
+                socket.read_exact(&mut length).map_err(proxy_failure)?;
+                length[0] as usize
+            }
+            _ => return Err(proxy_failure(invalid("invalid SOCKS5 reply"))),
+        };
+        let mut bound = vec![0; address_length + 2];
+        socket.read_exact(&mut bound).map_err(proxy_failure)?;
+        match reply[1] {
+            0 => Ok(()),
+            3 => Err(io::Error::other("network unreachable via SOCKS5 proxy")),
+            4 => Err(io::Error::other("host unreachable via SOCKS5 proxy")),
+            5 => Err(io::Error::new(io::ErrorKind::ConnectionRefused,
+                                    "connection refused via SOCKS5 proxy")),
+            6 => Err(io::Error::new(io::ErrorKind::TimedOut, "TTL expired via SOCKS5 proxy")),
+            2 => Err(proxy_failure(denied("connection not allowed by SOCKS5 proxy"))),
+            code => {
+                let message = format!("SOCKS5 proxy failed with code {}", code);
+                Err(proxy_failure(io::Error::other(message)))
+            }
+        }
+    }
+}
+
+/// Marker of I/O errors caused by a failure of the proxy itself, wrapping the original error.
+#[derive(Debug)]
+struct ProxyFailed(io::Error);
+
+impl fmt::Display for ProxyFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl StdError for ProxyFailed {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.0)
+    }
+}
+
+fn proxy_failure(error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), ProxyFailed(error))
+}
+
+/// Check whether connecting failed because of the SOCKS5 proxy.
+pub fn is_proxy_failure(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<ProxyFailed>())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn denied(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stream replaying `input` and recording everything written to it.
+    struct Mock {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Mock {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Mock {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn handshake(auth: Option<(&str, &str)>,
+                 input: &[u8],
+                 host: &str)
+                 -> (io::Result<()>, Vec<u8>) {
+        let proxy = Socks5 {
+            address: "proxy:1080".to_owned(),
+            auth: auth.map(|(user, password)| (user.to_owned(), password.to_owned())),
+        };
+        let mut mock = Mock {
+            input: io::Cursor::new(input.to_owned()),
+            output: Vec::new(),
+        };
+        let result = proxy.handshake(&mut mock, host, 443);
+        (result, mock.output)
+    }
+
+    #[test]
+    fn handshake_domain() {
+        let (result, output) = handshake(None, b"\x05\x00\x05\x00\x00\x01\0\0\0\0\0\0", "api");
+        result.unwrap();
+        assert_eq!(output, b"\x05\x01\x00\x05\x01\x00\x03\x03api\x01\xbb");
+    }
+
+    #[test]
+    fn handshake_auth() {
+        let input = b"\x05\x02\x01\x00\x05\x00\x00\x01\0\0\0\0\0\0";
+        let (result, output) = handshake(Some(("u", "pw")), input, "10.0.0.1");
+        result.unwrap();
+        assert_eq!(output,
+                   &b"\x05\x02\x00\x02\x01\x01u\x02pw\x05\x01\x00\x01\x0a\x00\x00\x01\x01\xbb"[..]);
+    }
+
+    #[test]
+    fn handshake_auth_rejected() {
+        let (result, _) = handshake(Some(("u", "pw")), b"\x05\x02\x01\x01", "api");
+        assert!(is_proxy_failure(&result.unwrap_err()));
+    }
+
+    #[test]
+    fn handshake_refused() {
+        let (result, _) = handshake(None, b"\x05\x00\x05\x05\x00\x01\0\0\0\0\0\0", "api");
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        assert!(!is_proxy_failure(&error));
+    }
+}
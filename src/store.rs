@@ -0,0 +1,169 @@
+//! Local cache of watched objects.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use {KubeObject, ResyncEvent, WatchEvent};
+
+/// Cache of current objects of type `T`, kept up to date by applying events of a watch. Objects
+/// are identified by keys computed from them, e.g. their namespace and name.
+///
+/// ```no_run
+/// use kubewatch::{Events, KubeObject, Store};
+///
+/// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+/// let mut store = Store::<KubeObject<serde_json::Value>>::by_name();
+/// let events = cluster.events_typed::<KubeObject<serde_json::Value>>("api/v1/pods").unwrap();
+/// for event in events {
+///     store.apply(event.unwrap());
+///     println!("{} pods", store.len());
+/// }
+/// ```
+pub struct Store<T> {
+    objects: HashMap<String, T>,
+    key: Box<dyn Fn(&T) -> String + Send + Sync>,
+}
+
+impl<T> Store<T> {
+    /// Initialize empty store identifying objects by `key`.
+    pub fn new<Key>(key: Key) -> Store<T>
+        where Key: Fn(&T) -> String + Send + Sync + 'static
+    {
+        Store {
+            objects: HashMap::new(),
+            key: Box::new(key),
+        }
+    }
+
+    /// Apply `event` to the store: added and modified objects are stored, deleted ones are
+    /// removed. Bookmarks and errors do not change it.
+    pub fn apply(&mut self, event: WatchEvent<T>) {
+        match event {
+            WatchEvent::Added(object) | WatchEvent::Modified(object) => {
+                self.objects.insert((self.key)(&object), object);
+            }
+            WatchEvent::Deleted(object) => {
+                self.objects.remove(&(self.key)(&object));
+            }
+            WatchEvent::Bookmark(_) | WatchEvent::Error(_) => (),
+        }
+    }
+
+    /// Apply `event` of `Cluster::watch_with_resync`, a list replaces all stored objects.
+    pub fn apply_resync(&mut self, event: ResyncEvent<T>) {
+        match event {
+            ResyncEvent::List(objects) => self.replace(objects),
+            ResyncEvent::Event(event) => self.apply(event),
+        }
+    }
+
+    /// Replace all stored objects by `objects`, e.g. by a fresh list of the resources.
+    pub fn replace(&mut self, objects: Vec<T>) {
+        self.objects.clear();
+        for object in objects {
+            self.objects.insert((self.key)(&object), object);
+        }
+    }
+
+    /// Object stored under given `key`.
+    pub fn get(&self, key: &str) -> Option<&T> {
+        self.objects.get(key)
+    }
+
+    /// Number of stored objects.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Check whether no object is stored.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Iterate over stored objects with their keys, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &T)> {
+        self.objects.iter()
+    }
+
+    /// Copy of all stored objects, in arbitrary order.
+    pub fn snapshot(&self) -> Vec<T>
+        where T: Clone
+    {
+        self.objects.values().cloned().collect()
+    }
+}
+
+impl<Spec> Store<KubeObject<Spec>> {
+    /// Initialize empty store identifying objects by their namespace and name (`namespace/name`,
+    /// or just `name` for cluster-scoped objects).
+    pub fn by_name() -> Store<KubeObject<Spec>> {
+        Store::new(|object: &KubeObject<Spec>| match object.metadata.namespace {
+            Some(ref namespace) => format!("{}/{}", namespace, object.metadata.name),
+            None => object.metadata.name.clone(),
+        })
+    }
+
+    /// Initialize empty store identifying objects by their UID.
+    pub fn by_uid() -> Store<KubeObject<Spec>> {
+        Store::new(|object: &KubeObject<Spec>| object.metadata.uid.clone())
+    }
+}
+
+impl<T> Extend<WatchEvent<T>> for Store<T> {
+    fn extend<Iter>(&mut self, events: Iter)
+        where Iter: IntoIterator<Item = WatchEvent<T>>
+    {
+        for event in events {
+            self.apply(event);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Store<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Store").field("objects", &self.objects).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ObjectMeta;
+
+    fn pod(namespace: &str, name: &str, spec: u32) -> KubeObject<u32> {
+        KubeObject {
+            api_version: "v1".to_owned(),
+            kind: "Pod".to_owned(),
+            metadata: ObjectMeta {
+                name: name.to_owned(),
+                namespace: Some(namespace.to_owned()),
+                ..Default::default()
+            },
+            spec,
+        }
+    }
+
+    #[test]
+    fn store() {
+        let mut store = Store::by_name();
+        store.extend(vec![WatchEvent::Added(pod("default", "a", 1)),
+                          WatchEvent::Added(pod("default", "b", 2)),
+                          WatchEvent::Modified(pod("default", "a", 3)),
+                          WatchEvent::Bookmark("42".to_owned()),
+                          WatchEvent::Deleted(pod("default", "b", 2))]);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get("default/a").unwrap().spec, 3);
+        assert_eq!(store.snapshot(), vec![pod("default", "a", 3)]);
+    }
+
+    #[test]
+    fn store_resync() {
+        let mut store = Store::new(|n: &u32| n.to_string());
+        store.apply_resync(ResyncEvent::List(vec![1, 2]));
+        store.apply_resync(ResyncEvent::Event(WatchEvent::Added(3)));
+        store.apply_resync(ResyncEvent::List(vec![2, 4]));
+        let mut snapshot = store.snapshot();
+        snapshot.sort();
+        assert_eq!(snapshot, vec![2, 4]);
+    }
+}
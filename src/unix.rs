@@ -0,0 +1,67 @@
+//! Connections to the API server over a Unix domain socket.
+
+use hyper;
+use hyper::net::{NetworkConnector, NetworkStream};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use watch::Interrupt;
+
+/// Connector opening connections to a Unix domain socket at `path`, regardless of the requested
+/// host. Connections are registered to `interrupt`, if set.
+pub struct UnixConnector {
+    pub path: PathBuf,
+    pub interrupt: Option<Interrupt>,
+}
+
+impl NetworkConnector for UnixConnector {
+    type Stream = UnixHttpStream;
+
+    fn connect(&self, _: &str, _: u16, _: &str) -> hyper::Result<UnixHttpStream> {
+        let socket = UnixStream::connect(&self.path)?;
+        if let Some(ref interrupt) = self.interrupt {
+            interrupt.register_unix(&socket)?;
+        }
+        Ok(UnixHttpStream(socket))
+    }
+}
+
+/// HTTP connection over a Unix domain socket.
+pub struct UnixHttpStream(UnixStream);
+
+impl Read for UnixHttpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for UnixHttpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl NetworkStream for UnixHttpStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        Err(io::Error::other("Unix domain socket has no IP address"))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.0.set_write_timeout(timeout)
+    }
+
+    fn close(&mut self, how: Shutdown) -> io::Result<()> {
+        self.0.shutdown(how)
+    }
+}
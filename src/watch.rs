@@ -0,0 +1,1964 @@
+//! Handle of a running watch and helpers used by its worker thread.
+
+use serde::de::Error as DeError;
+use serde::Deserialize;
+use serde_json::{self, Value};
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read};
+use std::marker::PhantomData;
+use std::net::{self, TcpStream};
+use std::ops::Deref;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Iter, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use format::{is_oversized_frame, oversized_frame, Header};
+use {Error, Format, Json, WatchOptions};
+
+/// How often a watch limited by `WatchOptions::max_duration` checks whether it already ended.
+const DEADLINE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A stream closed without any data sooner than this after its response arrived is considered
+/// cut and reported as `Error::EmptyStream`. Quiet streams closed later timed out on the server.
+const EMPTY_STREAM_WINDOW: Duration = Duration::from_secs(1);
+
+/// Maximal length of the raw event included in `Error::InvalidEvent`.
+const SNIPPET_BYTES: usize = 256;
+
+/// Handle of a running watch. Received events can be read by iterating over it, which blocks
+/// until the next event arrives and ends once the watch does, or through the dereferenced
+/// `Receiver`. Dropping the handle stops the watch.
+#[derive(Debug)]
+pub struct Watch<Event> {
+    events: Receiver<Result<Event, Error>>,
+    interrupt: Interrupt,
+    closed: Arc<AtomicBool>,
+    running: Option<Arc<AtomicBool>>,
+    pub(crate) progress: Arc<Progress>,
+}
+
+impl<Event> Watch<Event> {
+    /// Wrap `events` received from a worker thread which can be stopped via `interrupt`.
+    pub(crate) fn new(events: Receiver<Result<Event, Error>>,
+                      interrupt: Interrupt)
+                      -> Watch<Event> {
+        Watch {
+            events,
+            interrupt,
+            closed: Arc::default(),
+            running: None,
+            progress: Arc::default(),
+        }
+    }
+
+    /// Remember flag of `WatchOptions::running`, so it can be cleared by `shut_down`.
+    pub(crate) fn with_running(mut self, running: Option<Arc<AtomicBool>>) -> Watch<Event> {
+        self.running = running;
+        self
+    }
+
+    /// Resource version of the newest event delivered by the watch, or the version it was started
+    /// from if no event carried one yet. Once the watch ends, a new one can be resumed from it by
+    /// `WatchOptions::resource_version`. Merged watches (`Events::events_multi`) do not track it.
+    ///
+    /// ```no_run
+    /// use kubewatch::{Events, WatchOptions};
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster.events::<serde_json::Value>("api/v1/pods").unwrap();
+    /// for event in &events {
+    ///     println!("{:?}", event);
+    /// }
+    /// let options = WatchOptions {
+    ///     resource_version: events.resource_version(),
+    ///     ..Default::default()
+    /// };
+    /// let events = cluster.events_with_options::<serde_json::Value>("api/v1/pods", &options);
+    /// ```
+    pub fn resource_version(&self) -> Option<String> {
+        self.progress.version()
+    }
+
+    /// Snapshot of counters updated by the worker thread while the watch runs, e.g. to be
+    /// exported as metrics. Merged watches (`Events::events_multi`) do not track them.
+    ///
+    /// ```no_run
+    /// use kubewatch::Events;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster.events::<serde_json::Value>("api/v1/pods").unwrap();
+    /// for _ in &events {
+    ///     println!("{} events received so far", events.stats().events);
+    /// }
+    /// ```
+    pub fn stats(&self) -> Stats {
+        self.progress.stats()
+    }
+
+    /// Time when the last frame of the stream (an event, bookmark or error) was parsed, `None`
+    /// if none was yet. It allows detecting a watch which stopped receiving events although its
+    /// connection is still open, e.g. by a liveness probe.
+    ///
+    /// ```no_run
+    /// use kubewatch::Events;
+    /// use std::time::Duration;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster.events::<serde_json::Value>("api/v1/pods").unwrap();
+    /// let alive = events.last_event_at().is_some_and(|at| at.elapsed() < Duration::from_secs(60));
+    /// ```
+    pub fn last_event_at(&self) -> Option<Instant> {
+        *self.progress.last_event.lock().unwrap()
+    }
+
+    /// Check whether the server gracefully closed the watch, so resources should be listed
+    /// again. Once all events were received, `false` means that the watch failed with an error or
+    /// was stopped.
+    ///
+    /// ```no_run
+    /// use kubewatch::Events;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster.events::<serde_json::Value>("api/v1/pods").unwrap();
+    /// for event in &events {
+    ///     println!("{:?}", event);
+    /// }
+    /// if events.is_closed() {
+    ///     println!("watch closed by the server");
+    /// }
+    /// ```
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// How the watch ended, `None` while its worker thread still runs. Once all events were
+    /// received, it tells a watch closed by the server apart from one which failed with the
+    /// error received last, or which was stopped. Merged watches (`Events::events_multi`) and
+    /// those of `Cluster::watch_with_resync` do not track it.
+    ///
+    /// ```no_run
+    /// use kubewatch::{Events, WatchStatus};
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster.events::<serde_json::Value>("api/v1/pods").unwrap();
+    /// for event in &events {
+    ///     println!("{:?}", event);
+    /// }
+    /// match events.status() {
+    ///     Some(WatchStatus::Errored(message)) => println!("watch failed: {}", message),
+    ///     status => println!("watch ended: {:?}", status),
+    /// }
+    /// ```
+    pub fn status(&self) -> Option<WatchStatus> {
+        self.progress.status.lock().unwrap().clone()
+    }
+
+    /// Stop the watch and close its connection to the server. Events received before the call
+    /// can still be read.
+    pub fn stop(&self) {
+        self.interrupt.trigger();
+    }
+
+    /// Clear the `WatchOptions::running` flag the watch was started with, so all watches sharing
+    /// it stop once they receive their next event, and stop this watch right away.
+    ///
+    /// ```no_run
+    /// use kubewatch::{Events, WatchOptions};
+    /// use std::sync::atomic::AtomicBool;
+    /// use std::sync::Arc;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let options = WatchOptions {
+    ///     running: Some(Arc::new(AtomicBool::new(true))),
+    ///     ..Default::default()
+    /// };
+    /// let pods = cluster.events_with_options::<serde_json::Value>("api/v1/pods", &options);
+    /// let nodes = cluster.events_with_options::<serde_json::Value>("api/v1/nodes", &options);
+    /// // On SIGTERM, stops the watch of nodes as well.
+    /// pods.unwrap().shut_down();
+    /// ```
+    pub fn shut_down(&self) {
+        if let Some(ref running) = self.running {
+            running.store(false, Ordering::SeqCst);
+        }
+        self.interrupt.trigger();
+    }
+
+    /// Convert the watch into a handle which can be cloned and shared by multiple consumer
+    /// threads, e.g. to distribute events among workers.
+    ///
+    /// ```no_run
+    /// use kubewatch::Events;
+    ///
+    /// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+    /// let events = cluster.events::<serde_json::Value>("api/v1/pods").unwrap().shared();
+    /// let workers: Vec<_> = (0..4)
+    ///     .map(|_| {
+    ///         let events = events.clone();
+    ///         std::thread::spawn(move || for event in events {
+    ///             println!("{:?}", event);
+    ///         })
+    ///     })
+    ///     .collect();
+    /// ```
+    pub fn shared(self) -> SharedWatch<Event> {
+        SharedWatch {
+            interrupt: self.interrupt.clone(),
+            watch: Arc::new(Mutex::new(self)),
+        }
+    }
+}
+
+impl<Event> Deref for Watch<Event> {
+    type Target = Receiver<Result<Event, Error>>;
+
+    fn deref(&self) -> &Receiver<Result<Event, Error>> {
+        &self.events
+    }
+}
+
+impl<Event> Iterator for Watch<Event> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Result<Event, Error>> {
+        self.events.recv().ok()
+    }
+}
+
+impl<'a, Event> IntoIterator for &'a Watch<Event> {
+    type Item = Result<Event, Error>;
+    type IntoIter = Iter<'a, Result<Event, Error>>;
+
+    fn into_iter(self) -> Iter<'a, Result<Event, Error>> {
+        self.events.iter()
+    }
+}
+
+impl<Event> Drop for Watch<Event> {
+    fn drop(&mut self) {
+        self.interrupt.trigger();
+    }
+}
+
+/// Handle of a running watch shared by multiple consumers, created by `Watch::shared`. Every
+/// event is received by only one of its clones. The watch is stopped once all clones are dropped.
+#[derive(Debug)]
+pub struct SharedWatch<Event> {
+    watch: Arc<Mutex<Watch<Event>>>,
+    interrupt: Interrupt,
+}
+
+impl<Event> SharedWatch<Event> {
+    /// Block until the next event is received, `None` once the watch ended.
+    pub fn recv(&self) -> Option<Result<Event, Error>> {
+        self.watch.lock().unwrap().events.recv().ok()
+    }
+
+    /// Stop the watch for all consumers, see `Watch::stop`.
+    pub fn stop(&self) {
+        self.interrupt.trigger();
+    }
+}
+
+impl<Event> Clone for SharedWatch<Event> {
+    fn clone(&self) -> SharedWatch<Event> {
+        SharedWatch {
+            watch: self.watch.clone(),
+            interrupt: self.interrupt.clone(),
+        }
+    }
+}
+
+impl<Event> Iterator for SharedWatch<Event> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Result<Event, Error>> {
+        self.recv()
+    }
+}
+
+/// Handle of a running watch delivering events to a callback, created by `Cluster::events_with`.
+/// Dropping the handle does not stop the watch, it runs until the stream ends.
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+    interrupt: Interrupt,
+}
+
+impl StopHandle {
+    /// Stop the watch and close its connection to the server. The callback may still be running
+    /// with the last event when the call returns.
+    pub fn stop(&self) {
+        self.interrupt.trigger();
+    }
+}
+
+/// Token cancelling watches started with it in `WatchOptions::cancel`. Clones share the same
+/// state, so one can be kept while the other is passed to the watch.
+///
+/// ```no_run
+/// use kubewatch::{CancelToken, Events, WatchOptions};
+/// use std::time::Duration;
+///
+/// let cluster = kubewatch::Cluster::new("http://127.0.0.1:8080").unwrap();
+/// let cancel = CancelToken::new();
+/// let options = WatchOptions { cancel: Some(cancel.clone()), ..Default::default() };
+/// let events = cluster.events_with_options::<serde_json::Value>("api/v1/pods", &options);
+/// // Stop the watch once the deadline of the request passes.
+/// std::thread::spawn(move || {
+///     std::thread::sleep(Duration::from_secs(30));
+///     cancel.cancel();
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    interrupt: Interrupt,
+}
+
+impl CancelToken {
+    /// Create a token which has not been cancelled yet.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Stop all watches started with the token and close their connections. Watches started
+    /// with it afterwards are stopped right away.
+    pub fn cancel(&self) {
+        self.interrupt.trigger();
+    }
+
+    /// Check whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.interrupt.is_triggered()
+    }
+
+    /// Trigger `interrupt` of a watch once the token is cancelled.
+    pub(crate) fn link(&self, interrupt: Interrupt) {
+        self.interrupt.link(interrupt);
+    }
+}
+
+/// Watch read on demand by the caller's thread instead of a worker thread, see
+/// `Cluster::watch_reader`. Every call of `next` blocks until the next event is read from the
+/// stream. Dropping the reader closes its connection.
+pub struct WatchReader<Event> {
+    reader: Box<dyn BufRead + Send>,
+    interrupt: Interrupt,
+    options: WatchOptions,
+    format: Arc<dyn Format>,
+    progress: Progress,
+    deadline: Option<Instant>,
+    started: Instant,
+    closed: bool,
+    done: bool,
+    events: PhantomData<fn() -> Event>,
+}
+
+impl<Event> WatchReader<Event> {
+    /// Read events from `reader` in given `format`, stopping once `interrupt` is triggered.
+    /// `WatchOptions::max_duration` is checked before every read, `WatchOptions::idle_timeout`
+    /// does not apply, since no other thread can close the connection.
+    pub(crate) fn new<R>(reader: R,
+                         interrupt: Interrupt,
+                         options: WatchOptions,
+                         format: Arc<dyn Format>)
+                         -> WatchReader<Event>
+        where R: BufRead + Send + 'static
+    {
+        let progress = Progress::default();
+        progress.set_version(options.resource_version.clone());
+        WatchReader {
+            reader: Box::new(reader),
+            interrupt,
+            deadline: options.max_duration.map(|duration| Instant::now() + duration),
+            started: Instant::now(),
+            options,
+            format,
+            progress,
+            closed: false,
+            done: false,
+            events: PhantomData,
+        }
+    }
+
+    /// Resource version of the newest event read, see `Watch::resource_version`.
+    pub fn resource_version(&self) -> Option<String> {
+        self.progress.version()
+    }
+
+    /// Snapshot of counters of the read events, see `Watch::stats`.
+    pub fn stats(&self) -> Stats {
+        self.progress.stats()
+    }
+
+    /// Check whether the server gracefully closed the watch, see `Watch::is_closed`.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl<Event> Iterator for WatchReader<Event>
+    where Event: Deserialize
+{
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Result<Event, Error>> {
+        loop {
+            let expired = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            if self.done || expired || self.interrupt.is_triggered() || !self.options.is_running() {
+                self.done = true;
+                return None;
+            }
+            let frame = {
+                let mut reader = Counted {
+                    reader: &mut self.reader,
+                    bytes: &self.progress.bytes,
+                };
+                self.format.read_frame(&mut reader, self.options.max_event_bytes)
+            };
+            let event = match frame {
+                Some(Ok(raw)) => {
+                    match decode(&raw, &self.options, &*self.format, &self.progress) {
+                        Decoded::Event(event, frame_version) => {
+                            self.progress.processed(frame_version);
+                            event
+                        }
+                        Decoded::Skipped => continue,
+                        Decoded::Expired => {
+                            self.done = true;
+                            return Some(Err(Error::Expired));
+                        }
+                    }
+                }
+                Some(Err(ref e)) if is_oversized_frame(e) => Err(Error::FrameTooLarge),
+                Some(Err(e)) => {
+                    self.done = true;
+                    if self.interrupt.is_triggered() {
+                        return None;
+                    }
+                    return Some(Err(stream_error(e)));
+                }
+                None => {
+                    self.done = true;
+                    let empty = self.progress.bytes.load(Ordering::SeqCst) == 0;
+                    let early = self.started.elapsed() < EMPTY_STREAM_WINDOW;
+                    let interrupted = self.interrupt.is_triggered();
+                    if empty && early && self.options.timeout_seconds.is_none() && !interrupted {
+                        return Some(Err(Error::EmptyStream));
+                    }
+                    self.closed = !interrupted;
+                    return None;
+                }
+            };
+            self.progress.events.fetch_add(1, Ordering::SeqCst);
+            return Some(event);
+        }
+    }
+}
+
+/// How a watch ended, see `Watch::status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchStatus {
+    /// The server closed the stream and all its events were delivered.
+    Completed,
+    /// The watch failed with an error delivered as its last event, e.g. `Error::StreamIo` or
+    /// `Error::Expired`. `Error` cannot be cloned, so only its message is included.
+    Errored(String),
+    /// The watch was stopped, e.g. by `Watch::stop`, `WatchOptions::cancel` or
+    /// `WatchOptions::max_duration`.
+    Cancelled,
+}
+
+/// Snapshot of counters of a running watch, see `Watch::stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of events delivered to the consumer, including deserialization errors.
+    pub events: u64,
+    /// Number of frames which failed to deserialize, including skipped ones.
+    pub deserialization_errors: u64,
+    /// Number of times the watch was re-established by `Cluster::events_reconnecting`.
+    pub reconnects: u64,
+    /// Number of bytes of the event stream read, after decompression.
+    pub bytes: u64,
+}
+
+/// Progress of a watch shared between the `Watch` and its worker thread.
+#[derive(Debug, Default)]
+pub struct Progress {
+    version: Mutex<Option<String>>,
+    last_event: Mutex<Option<Instant>>,
+    /// Newest resource version of every existing object, keyed by its UID.
+    seen: Mutex<HashMap<String, String>>,
+    events: AtomicU64,
+    deserialization_errors: AtomicU64,
+    reconnects: AtomicU64,
+    bytes: AtomicU64,
+    /// Message of the error which ended the current connection, if any.
+    failure: Mutex<Option<String>>,
+    status: Mutex<Option<WatchStatus>>,
+}
+
+impl Progress {
+    /// Resource version of the newest delivered event.
+    pub fn version(&self) -> Option<String> {
+        self.version.lock().unwrap().clone()
+    }
+
+    /// Replace the resource version, e.g. by the one the watch is started from.
+    pub fn set_version(&self, version: Option<String>) {
+        *self.version.lock().unwrap() = version;
+    }
+
+    /// Count a re-established watch, forgetting the failure of the previous connection.
+    pub fn reconnected(&self) {
+        self.reconnects.fetch_add(1, Ordering::SeqCst);
+        *self.failure.lock().unwrap() = None;
+    }
+
+    /// Remember `error` which ended the watch, unless it is re-established.
+    pub fn fail(&self, error: &Error) {
+        let mut failure = self.failure.lock().unwrap();
+        if failure.is_none() {
+            *failure = Some(error.to_string());
+        }
+    }
+
+    /// Record that the worker thread ended: the watch failed if an error ended it, otherwise it
+    /// was `cancelled` or completed.
+    pub fn finish(&self, cancelled: bool) {
+        let status = match self.failure.lock().unwrap().clone() {
+            Some(message) => WatchStatus::Errored(message),
+            None if cancelled => WatchStatus::Cancelled,
+            None => WatchStatus::Completed,
+        };
+        *self.status.lock().unwrap() = Some(status);
+    }
+
+    fn stats(&self) -> Stats {
+        Stats {
+            events: self.events.load(Ordering::SeqCst),
+            deserialization_errors: self.deserialization_errors.load(Ordering::SeqCst),
+            reconnects: self.reconnects.load(Ordering::SeqCst),
+            bytes: self.bytes.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Check whether the frame of `header` repeats the last seen version of its object, and
+    /// remember the version otherwise. Deleted objects are forgotten.
+    fn is_repeated(&self, header: &FrameHeader) -> bool {
+        let metadata = &header.object.metadata;
+        let (uid, version) = match (&metadata.uid, &metadata.resource_version) {
+            (Some(uid), Some(version)) => (uid, version),
+            _ => return false,
+        };
+        let mut seen = self.seen.lock().unwrap();
+        if seen.get(uid) == Some(version) {
+            return true;
+        }
+        if header.event_type.as_deref() == Some("DELETED") {
+            seen.remove(uid);
+        } else {
+            seen.insert(uid.clone(), version.clone());
+        }
+        false
+    }
+
+    /// Replace the resource version by `version` of a processed frame, if the frame carried any.
+    fn processed(&self, version: Option<String>) {
+        if version.is_some() {
+            self.set_version(version);
+        }
+    }
+}
+
+/// Switch shared between a `Watch` and its worker thread, used to stop the worker even while it
+/// is blocked reading from the server.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    triggered: Arc<AtomicBool>,
+    socket: Arc<Mutex<Option<Socket>>>,
+    linked: Arc<Mutex<Vec<Interrupt>>>,
+}
+
+impl Interrupt {
+    /// Remember `socket` of the watch connection, so it can be shut down once triggered.
+    pub fn register(&self, socket: &TcpStream) -> io::Result<()> {
+        self.register_socket(Socket::Tcp(socket.try_clone()?))
+    }
+
+    /// Remember Unix domain `socket` of the watch connection, see `register`.
+    #[cfg(unix)]
+    pub fn register_unix(&self, socket: &UnixStream) -> io::Result<()> {
+        self.register_socket(Socket::Unix(socket.try_clone()?))
+    }
+
+    fn register_socket(&self, socket: Socket) -> io::Result<()> {
+        let mut registered = self.socket.lock().unwrap();
+        if self.is_triggered() {
+            socket.shutdown()?;
+        }
+        *registered = Some(socket);
+        Ok(())
+    }
+
+    /// Shut down the current connection without asking the worker to stop, e.g. so it can
+    /// reconnect.
+    pub fn disconnect(&self) {
+        if let Some(ref socket) = *self.socket.lock().unwrap() {
+            let _ = socket.shutdown();
+        }
+    }
+
+    /// Trigger the interrupt once `duration` passes, unless it is triggered sooner.
+    pub fn trigger_after(&self, duration: Duration) -> Result<(), Error> {
+        let interrupt = self.clone();
+        let deadline = Instant::now() + duration;
+        spawn_worker("deadline", move || {
+            while !interrupt.is_triggered() {
+                let now = Instant::now();
+                if now >= deadline {
+                    interrupt.trigger();
+                    return;
+                }
+                thread::sleep(cmp::min(deadline - now, DEADLINE_POLL_INTERVAL));
+            }
+        })
+    }
+
+    /// Trigger also `other` once this interrupt is triggered.
+    pub fn link(&self, other: Interrupt) {
+        if self.is_triggered() {
+            other.trigger();
+        }
+        self.linked.lock().unwrap().push(other);
+    }
+
+    /// Ask the worker to stop and shut down its connection, so any blocked read returns.
+    pub fn trigger(&self) {
+        self.triggered.store(true, Ordering::SeqCst);
+        self.disconnect();
+        for linked in self.linked.lock().unwrap().iter() {
+            linked.trigger();
+        }
+    }
+
+    /// Check whether the worker was asked to stop.
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+}
+
+/// Callback receiving events directly from a worker thread.
+type EventCallback<Event> = Box<dyn FnMut(Result<Event, Error>) + Send>;
+
+/// Socket of a watch connection registered to its `Interrupt`.
+#[derive(Debug)]
+enum Socket {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Socket {
+    fn shutdown(&self) -> io::Result<()> {
+        match *self {
+            Socket::Tcp(ref socket) => socket.shutdown(net::Shutdown::Both),
+            #[cfg(unix)]
+            Socket::Unix(ref socket) => socket.shutdown(net::Shutdown::Both),
+        }
+    }
+}
+
+/// Sending half of the channel between a worker thread and its `Watch`, or a callback invoked
+/// directly by the worker thread.
+pub enum EventSender<Event> {
+    Unbounded(Sender<Result<Event, Error>>),
+    /// Bounded channel with a function delivering `Error::ConsumerLagged` once it has room.
+    Bounded(SyncSender<Result<Event, Error>>, Box<dyn Fn() + Send>),
+    Callback(RefCell<EventCallback<Event>>),
+}
+
+/// Reason why `EventSender::send` did not deliver an event.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Undelivered {
+    /// The bounded channel is full, the consumer is notified by `Error::ConsumerLagged`.
+    Lagged,
+    /// The consumer is gone, e.g. it dropped the `Watch`.
+    Disconnected,
+}
+
+impl<Event> EventSender<Event> {
+    /// Send `event` to the consumer. If a bounded channel is full, the event is not delivered and
+    /// the caller is expected to end the watch. `Error::ConsumerLagged` is delivered as the last
+    /// event once the consumer catches up, without blocking the caller meanwhile.
+    pub fn send(&self, event: Result<Event, Error>) -> Result<(), Undelivered> {
+        match *self {
+            EventSender::Unbounded(ref tx) => tx.send(event).map_err(|_| Undelivered::Disconnected),
+            EventSender::Bounded(ref tx, ref notify_lagged) => {
+                match tx.try_send(event) {
+                    Ok(()) => Ok(()),
+                    Err(TrySendError::Full(_)) => {
+                        notify_lagged();
+                        Err(Undelivered::Lagged)
+                    }
+                    Err(TrySendError::Disconnected(_)) => Err(Undelivered::Disconnected),
+                }
+            }
+            EventSender::Callback(ref callback) => {
+                (callback.borrow_mut())(event);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Create channel between a worker thread and its `Watch`, holding at most `capacity` events if
+/// set.
+pub fn channel<Event>(capacity: Option<usize>)
+                      -> (EventSender<Event>, Receiver<Result<Event, Error>>)
+    where Event: Send + 'static
+{
+    match capacity {
+        Some(capacity) => {
+            let (tx, rx) = mpsc::sync_channel(capacity);
+            let lagged = tx.clone();
+            let notify_lagged = move || {
+                // The notification waits for a free slot in its own thread, so the worker can
+                // close the connection right away. It ends once the watch is dropped.
+                let lagged = lagged.clone();
+                let _ = spawn_worker("lag notification", move || {
+                    let _ = lagged.send(Err(Error::ConsumerLagged));
+                });
+            };
+            (EventSender::Bounded(tx, Box::new(notify_lagged)), rx)
+        }
+        None => {
+            let (tx, rx) = mpsc::channel();
+            (EventSender::Unbounded(tx), rx)
+        }
+    }
+}
+
+/// Spawn a worker thread deserializing events of resources `name` from `reader` in given `format`
+/// until the stream ends, the `Watch` is stopped or dropped. Events are buffered in a channel of
+/// given `capacity`, unbounded if not set.
+pub fn spawn<Event, R>(name: &str,
+                       reader: R,
+                       interrupt: Interrupt,
+                       options: WatchOptions,
+                       capacity: Option<usize>,
+                       format: Arc<dyn Format>)
+                       -> Result<Watch<Event>, Error>
+    where Event: Deserialize + Send + 'static,
+          R: BufRead + Send + 'static
+{
+    spawn_with(name, reader, interrupt, options, capacity, format, decode)
+}
+
+/// Spawn a worker thread reading only types of events of resources `name` from `reader` in given
+/// `format`, see `spawn`.
+pub fn spawn_types<R>(name: &str,
+                      reader: R,
+                      interrupt: Interrupt,
+                      options: WatchOptions,
+                      capacity: Option<usize>,
+                      format: Arc<dyn Format>)
+                      -> Result<Watch<String>, Error>
+    where R: BufRead + Send + 'static
+{
+    spawn_with(name, reader, interrupt, options, capacity, format, decode_type)
+}
+
+/// Spawn a worker thread forwarding events of resources `name` decoded by `decoder`, see `spawn`.
+fn spawn_with<Event, R>(name: &str,
+                        reader: R,
+                        interrupt: Interrupt,
+                        options: WatchOptions,
+                        capacity: Option<usize>,
+                        format: Arc<dyn Format>,
+                        decoder: Decoder<Event>)
+                        -> Result<Watch<Event>, Error>
+    where Event: Send + 'static,
+          R: BufRead + Send + 'static
+{
+    let (tx, rx) = channel(capacity);
+    let watch = Watch::new(rx, interrupt).with_running(options.running.clone());
+    let worker_interrupt = watch.interrupt.clone();
+    let closed = watch.closed.clone();
+    let progress = watch.progress.clone();
+    progress.set_version(options.resource_version.clone());
+    spawn_worker(name, move || {
+        // A shut down connection ends the stream the same way as one closed by the server.
+        let end = forward_with(reader,
+                               &tx,
+                               &worker_interrupt,
+                               &options,
+                               &*format,
+                               &progress,
+                               decoder);
+        if end == End::Closed && !worker_interrupt.is_triggered() {
+            closed.store(true, Ordering::SeqCst);
+        }
+        progress.finish(worker_interrupt.is_triggered());
+    })?;
+    Ok(watch)
+}
+
+/// Spawn a worker thread deserializing events of resources `name` from JSON frames of `script`,
+/// delivering its errors in between as they are. The stream ends after the last item.
+pub fn spawn_script<Event>(name: &str,
+                           script: Vec<Result<Vec<u8>, Error>>,
+                           options: WatchOptions)
+                           -> Result<Watch<Event>, Error>
+    where Event: Deserialize + Send + 'static
+{
+    let (tx, rx) = channel(None);
+    let watch = Watch::new(rx, Interrupt::default()).with_running(options.running.clone());
+    let interrupt = watch.interrupt.clone();
+    let closed = watch.closed.clone();
+    let progress = watch.progress.clone();
+    progress.set_version(options.resource_version.clone());
+    spawn_worker(name, move || {
+        for item in script {
+            let end = match item {
+                Ok(frames) => forward(&frames[..], &tx, &interrupt, &options, &Json, &progress),
+                Err(error) => if tx.send(Err(error)).is_ok() { End::Closed } else { End::Stopped },
+            };
+            if end != End::Closed {
+                progress.finish(interrupt.is_triggered());
+                return;
+            }
+        }
+        if !interrupt.is_triggered() {
+            closed.store(true, Ordering::SeqCst);
+        }
+        progress.finish(interrupt.is_triggered());
+    })?;
+    Ok(watch)
+}
+
+/// Spawn a worker thread deserializing events of resources `name` from `reader` in given `format`
+/// and passing them to `callback`, until the stream ends or the returned handle is stopped.
+pub fn spawn_callback<Event, R, Callback>(name: &str,
+                                          reader: R,
+                                          interrupt: Interrupt,
+                                          options: WatchOptions,
+                                          format: Arc<dyn Format>,
+                                          callback: Callback)
+                                          -> Result<StopHandle, Error>
+    where Event: Deserialize + 'static,
+          R: BufRead + Send + 'static,
+          Callback: FnMut(Result<Event, Error>) + Send + 'static
+{
+    let handle = StopHandle { interrupt: interrupt.clone() };
+    spawn_worker(name, move || {
+        let tx = EventSender::Callback(RefCell::new(Box::new(callback)));
+        let progress = Progress::default();
+        forward(reader, &tx, &interrupt, &options, &*format, &progress);
+    })?;
+    Ok(handle)
+}
+
+/// Spawn a thread running `worker`, named after watched resources `name` so it can be told apart
+/// in debuggers.
+pub fn spawn_worker<Worker>(name: &str, worker: Worker) -> Result<(), Error>
+    where Worker: FnOnce() + Send + 'static
+{
+    thread::Builder::new()
+        .name(format!("kubewatch {}", name))
+        .spawn(worker)
+        .map(|_| ())
+        .map_err(Error::SpawnFailed)
+}
+
+/// Merge `watches` into a single one, tagging their events with the name they are paired with.
+/// Stopping the merged watch stops all of them, it is closed once all of them are closed.
+pub fn merge<Event>(watches: Vec<(String, Watch<Event>)>)
+                    -> Result<Watch<(String, Event)>, Error>
+    where Event: Send + 'static
+{
+    let (tx, rx) = mpsc::channel();
+    let merged = Watch::new(rx, Interrupt::default());
+    let open = Arc::new(AtomicUsize::new(watches.len()));
+    for (name, watch) in watches {
+        merged.interrupt.link(watch.interrupt.clone());
+        let tx = tx.clone();
+        let open = open.clone();
+        let closed = merged.closed.clone();
+        let worker_name = name.clone();
+        spawn_worker(&worker_name, move || {
+            for event in &watch {
+                if tx.send(event.map(|event| (name.clone(), event))).is_err() {
+                    return;
+                }
+            }
+            if watch.is_closed() && open.fetch_sub(1, Ordering::SeqCst) == 1 {
+                closed.store(true, Ordering::SeqCst);
+            }
+        })?;
+    }
+    Ok(merged)
+}
+
+/// Reason why `forward` returned.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum End {
+    /// The stream ended, e.g. because the server closed it.
+    Closed,
+    /// Reading of the stream failed, the error was delivered to the consumer.
+    Failed,
+    /// The watch should not continue, because it was stopped, its resource version expired or
+    /// the consumer is gone or lagging behind.
+    Stopped,
+}
+
+/// Deserialize events from `reader` in given `format` and send them through `tx`, recording them
+/// in `progress`. If an event cannot be delivered, `interrupt` is triggered to close the
+/// connection. If no event arrives within `WatchOptions::idle_timeout`, the connection is closed
+/// and `Error::IdleTimeout` delivered. A stream closed right after it was opened without sending
+/// any data, although no `WatchOptions::timeout_seconds` asked for it, is reported as
+/// `Error::EmptyStream`.
+pub fn forward<Event, R>(reader: R,
+                         tx: &EventSender<Event>,
+                         interrupt: &Interrupt,
+                         options: &WatchOptions,
+                         format: &dyn Format,
+                         progress: &Progress)
+                         -> End
+    where Event: Deserialize,
+          R: BufRead
+{
+    forward_with(reader, tx, interrupt, options, format, progress, decode)
+}
+
+/// Forward events decoded from frames by `decoder`, see `forward`.
+fn forward_with<Event, R>(reader: R,
+                          tx: &EventSender<Event>,
+                          interrupt: &Interrupt,
+                          options: &WatchOptions,
+                          format: &dyn Format,
+                          progress: &Progress,
+                          decoder: Decoder<Event>)
+                          -> End
+    where R: BufRead
+{
+    let mut reader = Counted {
+        reader,
+        bytes: &progress.bytes,
+    };
+    let start = progress.bytes.load(Ordering::SeqCst);
+    let started = Instant::now();
+    let idle = Arc::new(AtomicBool::new(false));
+    let heartbeat = match options.idle_timeout {
+        Some(timeout) => {
+            match watch_idle(timeout, interrupt.clone(), idle.clone()) {
+                Ok(heartbeat) => Some(heartbeat),
+                Err(error) => return fail(tx, progress, error),
+            }
+        }
+        None => None,
+    };
+    while let Some(frame) = format.read_frame(&mut reader, options.max_event_bytes) {
+        if let Some(ref heartbeat) = heartbeat {
+            let _ = heartbeat.send(());
+        }
+        if idle.load(Ordering::SeqCst) && !interrupt.is_triggered() {
+            return fail(tx, progress, Error::IdleTimeout);
+        }
+        if interrupt.is_triggered() {
+            return End::Stopped;
+        }
+        if !options.is_running() {
+            interrupt.trigger();
+            return End::Stopped;
+        }
+        let (event, frame_version) = match frame {
+            Ok(raw) => {
+                match decoder(&raw, options, format, progress) {
+                    Decoded::Event(event, frame_version) => (event, frame_version),
+                    Decoded::Skipped => continue,
+                    Decoded::Expired => {
+                        fail(tx, progress, Error::Expired);
+                        return End::Stopped;
+                    }
+                }
+            }
+            Err(ref e) if is_oversized_frame(e) => (Err(Error::FrameTooLarge), None),
+            Err(e) => {
+                // The connection is broken, nothing more can be read from it.
+                let error = stream_error(e);
+                warn!("reading watch stream failed: {}", error);
+                return fail(tx, progress, error);
+            }
+        };
+        if let Err(undelivered) = tx.send(event) {
+            // Shut down the connection right away, so the server does not keep streaming to a
+            // consumer which is gone or lagging behind. Only a lagging consumer can still see it.
+            if undelivered == Undelivered::Lagged {
+                progress.fail(&Error::ConsumerLagged);
+            }
+            interrupt.trigger();
+            return End::Stopped;
+        }
+        progress.events.fetch_add(1, Ordering::SeqCst);
+        progress.processed(frame_version);
+    }
+    if idle.load(Ordering::SeqCst) && !interrupt.is_triggered() {
+        warn!("watch stream idle for too long, closing it");
+        return fail(tx, progress, Error::IdleTimeout);
+    }
+    let empty = progress.bytes.load(Ordering::SeqCst) == start;
+    let early = started.elapsed() < EMPTY_STREAM_WINDOW;
+    if empty && early && options.timeout_seconds.is_none() && !interrupt.is_triggered() {
+        warn!("watch stream closed before sending anything");
+        return fail(tx, progress, Error::EmptyStream);
+    }
+    debug!("watch stream closed");
+    End::Closed
+}
+
+/// Outcome of decoding a single frame of the stream.
+enum Decoded<Event> {
+    /// Event to deliver, with the resource version carried by its frame.
+    Event(Result<Event, Error>, Option<String>),
+    /// The frame is dropped, because it repeats a seen event or it is malformed and
+    /// `WatchOptions::skip_errors` is set.
+    Skipped,
+    /// The frame reports that the watched resource version expired.
+    Expired,
+}
+
+/// Decoder of a single frame of the stream, e.g. `decode`.
+type Decoder<Event> = fn(&[u8], &WatchOptions, &dyn Format, &Progress) -> Decoded<Event>;
+
+/// Decode `raw` frame in given `format` into an event, recording it in `progress`.
+fn decode<Event>(raw: &[u8],
+                 options: &WatchOptions,
+                 format: &dyn Format,
+                 progress: &Progress)
+                 -> Decoded<Event>
+    where Event: Deserialize
+{
+    // JSON frames are deserialized directly, only their header is read before.
+    let frame = if format.is_json() {
+        check_json_depth(raw, options.max_json_depth)
+            .and_then(|()| FrameHeader::from_json(raw))
+            .map(|header| (header, None))
+    } else {
+        format.decode(raw)
+            .and_then(|frame| check_depth(frame, options.max_json_depth))
+            .map(|frame| (FrameHeader::from_value(&frame), Some(frame)))
+    };
+    if frame.is_ok() {
+        *progress.last_event.lock().unwrap() = Some(Instant::now());
+    }
+    if frame.as_ref().is_ok_and(|frame| frame.0.is_expired()) {
+        return Decoded::Expired;
+    }
+    let frame_version = frame.as_ref()
+        .ok()
+        .and_then(|frame| frame.0.object.metadata.resource_version.clone());
+    if options.dedup && frame.as_ref().is_ok_and(|frame| progress.is_repeated(&frame.0)) {
+        return Decoded::Skipped;
+    }
+    let event = frame.and_then(|(_, value)| match value {
+            Some(value) => serde_json::from_value(value),
+            None => serde_json::from_slice(raw),
+        })
+        .map_err(|e| Error::InvalidEvent(e, snippet(raw)));
+    if let Err(ref e) = event {
+        progress.deserialization_errors.fetch_add(1, Ordering::SeqCst);
+        if options.skip_errors {
+            warn!("skipping event: {}", e);
+            progress.processed(frame_version);
+            return Decoded::Skipped;
+        }
+    }
+    Decoded::Event(event, frame_version)
+}
+
+/// Decode only the type of the event in `raw` frame, see `decode`. Whole frames are decoded only
+/// to recognize repeated events if `WatchOptions::dedup` is set, and expired resource versions.
+fn decode_type(raw: &[u8],
+               options: &WatchOptions,
+               format: &dyn Format,
+               progress: &Progress)
+               -> Decoded<String> {
+    if options.dedup {
+        return match decode::<Header>(raw, options, format, progress) {
+            Decoded::Event(event, version) => {
+                Decoded::Event(event.map(|header| header.event_type), version)
+            }
+            Decoded::Skipped => Decoded::Skipped,
+            Decoded::Expired => Decoded::Expired,
+        };
+    }
+    let event = format.event_type(raw).map_err(|e| Error::InvalidEvent(e, snippet(raw)));
+    match event {
+        Ok(ref event_type) => {
+            let expired = |frame: Value| FrameHeader::from_value(&frame).is_expired();
+            if event_type == "ERROR" && format.decode(raw).is_ok_and(expired) {
+                return Decoded::Expired;
+            }
+            *progress.last_event.lock().unwrap() = Some(Instant::now());
+        }
+        Err(ref e) => {
+            progress.deserialization_errors.fetch_add(1, Ordering::SeqCst);
+            if options.skip_errors {
+                warn!("skipping event: {}", e);
+                return Decoded::Skipped;
+            }
+        }
+    }
+    Decoded::Event(event, None)
+}
+
+/// Error of a broken connection, after which nothing more can be read from the stream.
+fn stream_error(error: io::Error) -> Error {
+    if is_timeout(&error) {
+        Error::ReadTimeout
+    } else {
+        Error::StreamIo(error)
+    }
+}
+
+/// Deliver `error` which ended the stream through `tx`, recording it in `progress`.
+fn fail<Event>(tx: &EventSender<Event>, progress: &Progress, error: Error) -> End {
+    progress.fail(&error);
+    if tx.send(Err(error)).is_ok() { End::Failed } else { End::Stopped }
+}
+
+/// Spawn a thread disconnecting `interrupt` and setting `idle` unless a heartbeat is sent through
+/// the returned channel every `timeout`. The thread ends once the channel is dropped.
+fn watch_idle(timeout: Duration,
+              interrupt: Interrupt,
+              idle: Arc<AtomicBool>)
+              -> Result<Sender<()>, Error> {
+    let (heartbeat, heartbeats) = mpsc::channel();
+    spawn_worker("idle timeout", move || loop {
+        match heartbeats.recv_timeout(timeout) {
+            Ok(()) => continue,
+            Err(RecvTimeoutError::Timeout) => {
+                idle.store(true, Ordering::SeqCst);
+                interrupt.disconnect();
+                return;
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    })?;
+    Ok(heartbeat)
+}
+
+/// Reader counting consumed `bytes`.
+struct Counted<'a, R> {
+    reader: R,
+    bytes: &'a AtomicU64,
+}
+
+impl<'a, R: Read> Read for Counted<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.bytes.fetch_add(read as u64, Ordering::SeqCst);
+        Ok(read)
+    }
+}
+
+impl<'a, R: BufRead> BufRead for Counted<'a, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.bytes.fetch_add(amount as u64, Ordering::SeqCst);
+        self.reader.consume(amount)
+    }
+}
+
+/// Lossy text of `frame`, truncated to `SNIPPET_BYTES`.
+fn snippet(frame: &[u8]) -> String {
+    if frame.len() <= SNIPPET_BYTES {
+        return String::from_utf8_lossy(frame).into_owned();
+    }
+    format!("{}...", String::from_utf8_lossy(&frame[..SNIPPET_BYTES]))
+}
+
+/// Reject `frame` nested deeper than `max_depth` levels of objects and arrays, if set.
+fn check_depth(frame: Value, max_depth: Option<usize>) -> Result<Value, serde_json::Error> {
+    let max_depth = match max_depth {
+        Some(max_depth) => max_depth,
+        None => return Ok(frame),
+    };
+    let mut stack = vec![(&frame, 0)];
+    while let Some((value, depth)) = stack.pop() {
+        let children: Vec<&Value> = match *value {
+            Value::Array(ref values) => values.iter().collect(),
+            Value::Object(ref values) => values.values().collect(),
+            _ => continue,
+        };
+        if depth >= max_depth {
+            return Err(too_deep(max_depth));
+        }
+        stack.extend(children.into_iter().map(|child| (child, depth + 1)));
+    }
+    Ok(frame)
+}
+
+/// Reject JSON `frame` nested deeper than `max_depth` levels of objects and arrays, if set,
+/// without parsing it.
+fn check_json_depth(frame: &[u8], max_depth: Option<usize>) -> Result<(), serde_json::Error> {
+    let max_depth = match max_depth {
+        Some(max_depth) => max_depth,
+        None => return Ok(()),
+    };
+    let (mut depth, mut in_string, mut escaped) = (0, false, false);
+    for &byte in frame {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(too_deep(max_depth));
+                }
+            }
+            b'}' | b']' => depth = cmp::max(depth, 1) - 1,
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn too_deep(max_depth: usize) -> serde_json::Error {
+    DeError::custom(format!("event is nested deeper than {} levels", max_depth))
+}
+
+/// Fields of a watch event frame read before the event is deserialized, other fields are
+/// skipped without being decoded.
+#[derive(Deserialize, Default)]
+struct FrameHeader {
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    #[serde(default)]
+    object: ObjectHeader,
+}
+
+#[derive(Deserialize, Default)]
+struct ObjectHeader {
+    code: Option<u64>,
+    #[serde(default)]
+    metadata: MetadataHeader,
+}
+
+#[derive(Deserialize, Default)]
+struct MetadataHeader {
+    uid: Option<String>,
+    #[serde(rename = "resourceVersion")]
+    resource_version: Option<String>,
+}
+
+impl FrameHeader {
+    /// Read header of JSON `frame`. Frames which are not shaped as watch events, e.g. plain
+    /// numbers, have an empty header.
+    fn from_json(frame: &[u8]) -> Result<FrameHeader, serde_json::Error> {
+        match serde_json::from_slice(frame) {
+            Err(ref e) if e.is_data() => Ok(FrameHeader::default()),
+            header => header,
+        }
+    }
+
+    /// Read header of a decoded `frame`.
+    fn from_value(frame: &Value) -> FrameHeader {
+        let string = |pointer| frame.pointer(pointer).and_then(Value::as_str).map(str::to_owned);
+        FrameHeader {
+            event_type: string("/type"),
+            object: ObjectHeader {
+                code: frame.pointer("/object/code").and_then(Value::as_u64),
+                metadata: MetadataHeader {
+                    uid: string("/object/metadata/uid"),
+                    resource_version: string("/object/metadata/resourceVersion"),
+                },
+            },
+        }
+    }
+
+    /// Check whether the frame is an `ERROR` event reporting that the watched resource version
+    /// is too old (410 Gone).
+    fn is_expired(&self) -> bool {
+        self.event_type.as_deref() == Some("ERROR") && self.object.code == Some(410)
+    }
+}
+
+/// Iterator splitting a buffered stream into separate top-level JSON values. Bytes which do not
+/// form a valid value are returned as a separate frame, so the stream can continue with the next
+/// value. The stream is scanned chunk by chunk, as returned by the reader.
+pub struct Frames<R> {
+    reader: R,
+    max_bytes: Option<usize>,
+}
+
+impl<R> Frames<R> {
+    pub fn new(reader: R) -> Frames<R> {
+        Frames {
+            reader,
+            max_bytes: None,
+        }
+    }
+
+    /// Skip frames longer than `max_bytes` without buffering them, returning an `OversizedFrame`
+    /// error instead.
+    pub fn max_bytes(mut self, max_bytes: Option<usize>) -> Frames<R> {
+        self.max_bytes = max_bytes;
+        self
+    }
+}
+
+impl<R> Iterator for Frames<R>
+    where R: BufRead
+{
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<io::Result<Vec<u8>>> {
+        let mut frame = Vec::new();
+        let mut length = 0;
+        let mut oversized = false;
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        loop {
+            let (consumed, complete) = {
+                let chunk = match self.reader.fill_buf() {
+                    Ok(chunk) => chunk,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Some(Err(e)),
+                };
+                if chunk.is_empty() {
+                    return match length {
+                        0 => None,
+                        _ if oversized => Some(Err(oversized_frame())),
+                        _ => Some(Ok(frame)),
+                    };
+                }
+                // Part of the chunk belonging to the frame, and how much of it is consumed.
+                let mut start = 0;
+                let mut end = None;
+                for (i, &byte) in chunk.iter().enumerate() {
+                    let empty = length == 0 && i == start;
+                    if in_string {
+                        if escaped {
+                            escaped = false;
+                        } else if byte == b'\\' {
+                            escaped = true;
+                        } else if byte == b'"' {
+                            in_string = false;
+                            if depth == 0 {
+                                end = Some((i + 1, i + 1));
+                                break;
+                            }
+                        }
+                        continue;
+                    }
+                    match byte {
+                        b'{' | b'[' | b'"' if depth == 0 && !empty => {
+                            end = Some((i, i));
+                            break;
+                        }
+                        b' ' | b'\t' | b'\r' | b'\n' if depth == 0 => {
+                            if empty {
+                                start = i + 1;
+                            } else {
+                                end = Some((i, i + 1));
+                                break;
+                            }
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' if depth > 0 => {
+                            depth -= 1;
+                            if depth == 0 {
+                                end = Some((i + 1, i + 1));
+                                break;
+                            }
+                        }
+                        b'"' => in_string = true,
+                        _ => (),
+                    }
+                }
+                let complete = end.is_some();
+                let (end, consumed) = end.unwrap_or((chunk.len(), chunk.len()));
+                length += end - start;
+                if self.max_bytes.is_some_and(|max| length > max) {
+                    // Drop what was buffered, the rest of the frame is only scanned.
+                    oversized = true;
+                    frame = Vec::new();
+                } else {
+                    frame.extend_from_slice(&chunk[start..end]);
+                }
+                (consumed, complete)
+            };
+            self.reader.consume(consumed);
+            if complete && oversized {
+                return Some(Err(oversized_frame()));
+            }
+            if complete {
+                return Some(Ok(frame));
+            }
+        }
+    }
+}
+
+/// Reader of bytes produced by an iterator. Every read returns a single byte, so no more bytes
+/// are requested from the iterator than necessary.
+pub struct IterReader<Iter>(pub Iter);
+
+impl<Iter> Read for IterReader<Iter>
+    where Iter: Iterator<Item = io::Result<u8>>
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.0.next() {
+            Some(Ok(byte)) => {
+                buf[0] = byte;
+                Ok(1)
+            }
+            Some(Err(e)) => Err(e),
+            None => Ok(0),
+        }
+    }
+}
+
+/// Check whether I/O operation failed due to an expired timeout.
+pub fn is_timeout(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Json;
+
+    /// Spawn a worker reading JSON events from `reader`.
+    fn spawn_json<Event, R>(reader: R, options: WatchOptions) -> Watch<Event>
+        where Event: Deserialize + Send + 'static,
+              R: BufRead + Send + 'static
+    {
+        spawn("test", reader, Interrupt::default(), options, None, Arc::new(Json)).unwrap()
+    }
+
+    fn frames(input: &str) -> Vec<String> {
+        let frames: Vec<_> = Frames::new(input.as_bytes())
+            .map(|frame| String::from_utf8(frame.unwrap()).unwrap())
+            .collect();
+        // Frames must not depend on how the stream is split into chunks.
+        for capacity in 1..4 {
+            let chunked: Vec<_> = Frames::new(io::BufReader::with_capacity(capacity,
+                                                                           input.as_bytes()))
+                .map(|frame| String::from_utf8(frame.unwrap()).unwrap())
+                .collect();
+            assert_eq!(chunked, frames);
+        }
+        frames
+    }
+
+    #[test]
+    fn frames_objects() {
+        assert_eq!(frames(r#"{"a": {"b": "}"}} [1, 2]{"c": "\"{"}"#),
+                   vec![r#"{"a": {"b": "}"}}"#, "[1, 2]", r#"{"c": "\"{"}"#]);
+    }
+
+    #[test]
+    fn frames_scalars() {
+        assert_eq!(frames(r#"1 "two"true{}"#), vec!["1", r#""two""#, "true", "{}"]);
+    }
+
+    #[test]
+    fn frames_garbage() {
+        assert_eq!(frames(r#"{"a": 1}garbage{"b": 2}"#),
+                   vec![r#"{"a": 1}"#, "garbage", r#"{"b": 2}"#]);
+    }
+
+    #[test]
+    fn frames_max_bytes() {
+        for capacity in 1..4 {
+            let input = io::BufReader::with_capacity(capacity, &br#"{"a": 1} 2 {"bcd": 3} 4"#[..]);
+            let frames: Vec<_> = Frames::new(input)
+                .max_bytes(Some(8))
+                .map(|frame| frame.map_err(|e| is_oversized_frame(&e)))
+                .collect();
+            assert_eq!(frames,
+                       vec![Ok(br#"{"a": 1}"#.to_vec()), Ok(b"2".to_vec()), Err(true),
+                            Ok(b"4".to_vec())]);
+        }
+    }
+
+    #[test]
+    fn iter_reader() {
+        let mut reader = IterReader(vec![Ok(b'a'), Ok(b'b')].into_iter());
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "ab");
+    }
+
+    #[test]
+    fn interrupt_link() {
+        let interrupt = Interrupt::default();
+        let linked = Interrupt::default();
+        interrupt.link(linked.clone());
+        interrupt.trigger();
+        assert!(linked.is_triggered());
+
+        let late = Interrupt::default();
+        interrupt.link(late.clone());
+        assert!(late.is_triggered());
+    }
+
+    #[test]
+    fn merge_stop() {
+        let (_tx, rx) = mpsc::channel::<Result<(), Error>>();
+        let interrupt = Interrupt::default();
+        let watch = merge(vec![("pods".to_owned(), Watch::new(rx, interrupt.clone()))]).unwrap();
+        watch.stop();
+        assert!(interrupt.is_triggered());
+    }
+
+    #[test]
+    fn forward_lagged() {
+        let (tx, rx) = channel::<u32>(Some(1));
+        let progress = Progress::default();
+        let options = WatchOptions::default();
+        let interrupt = Interrupt::default();
+        // The worker ends without waiting for the consumer to receive anything.
+        let end = forward(&b"1 2 3"[..], &tx, &interrupt, &options, &Json, &progress);
+        assert_eq!(end, End::Stopped);
+        assert!(interrupt.is_triggered());
+        assert_eq!(*progress.failure.lock().unwrap(), Some(Error::ConsumerLagged.to_string()));
+        drop(tx);
+        assert_eq!(rx.recv().unwrap().unwrap(), 1);
+        assert!(matches!(rx.recv().unwrap(), Err(Error::ConsumerLagged)));
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn forward_consumer_gone() {
+        let (tx, rx) = channel::<u32>(None);
+        drop(rx);
+        let interrupt = Interrupt::default();
+        let progress = Progress::default();
+        let options = WatchOptions::default();
+        let end = forward(&b"1 2 3"[..], &tx, &interrupt, &options, &Json, &progress);
+        assert_eq!(end, End::Stopped);
+        assert!(interrupt.is_triggered());
+        assert_eq!(*progress.failure.lock().unwrap(), None);
+    }
+
+    #[test]
+    fn forward_consumer_gone_bounded() {
+        let (tx, rx) = channel::<u32>(Some(1));
+        drop(rx);
+        let progress = Progress::default();
+        let options = WatchOptions::default();
+        let end = forward(&b"1 2 3"[..], &tx, &Interrupt::default(), &options, &Json, &progress);
+        assert_eq!(end, End::Stopped);
+        assert_eq!(*progress.failure.lock().unwrap(), None);
+    }
+
+    #[test]
+    fn spawn_closed() {
+        let watch = spawn_json::<u32, _>(&b"1 2"[..], Default::default());
+        assert_eq!(watch.iter().map(Result::unwrap).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(watch.is_closed());
+        assert_eq!(watch.status(), Some(WatchStatus::Completed));
+    }
+
+    #[test]
+    fn spawn_cancelled() {
+        let reader = io::BufReader::new(IterReader(b"1 ".iter().cloned().cycle().map(Ok)));
+        let watch = spawn_json::<u32, _>(reader, Default::default());
+        assert_eq!(watch.status(), None);
+        watch.stop();
+        assert!(watch.iter().all(|event| event.is_ok()));
+        assert_eq!(watch.status(), Some(WatchStatus::Cancelled));
+    }
+
+    #[test]
+    fn iterator() {
+        let watch = spawn_json::<u32, _>(&b"1 2 3"[..], Default::default());
+        let odd: Vec<_> = watch.map(Result::unwrap).filter(|n| n % 2 == 1).collect();
+        assert_eq!(odd, vec![1, 3]);
+    }
+
+    #[test]
+    fn spawn_resource_version() {
+        let frames = br#"{"type": "ADDED", "object": {"metadata": {"resourceVersion": "1"}}}
+            {"type": "MODIFIED", "object": {"metadata": {"resourceVersion": "2"}}}
+            {"type": "MODIFIED", "object": {}}"#;
+        let options = WatchOptions {
+            resource_version: Some("0".to_owned()),
+            ..Default::default()
+        };
+        let watch = spawn_json::<Value, _>(&frames[..], options);
+        assert_eq!(watch.resource_version(), Some("0".to_owned()));
+        assert_eq!(watch.iter().count(), 3);
+        assert_eq!(watch.resource_version(), Some("2".to_owned()));
+    }
+
+    #[test]
+    fn spawn_stats() {
+        let options = WatchOptions {
+            skip_errors: true,
+            ..Default::default()
+        };
+        let watch = spawn_json::<u32, _>(&b"1 \"a\" 2"[..], options);
+        assert_eq!(watch.iter().count(), 2);
+        let stats = Stats {
+            events: 2,
+            deserialization_errors: 1,
+            reconnects: 0,
+            bytes: 7,
+        };
+        assert_eq!(watch.stats(), stats);
+    }
+
+    #[test]
+    fn spawn_max_event_bytes() {
+        let options = WatchOptions {
+            max_event_bytes: Some(4),
+            ..Default::default()
+        };
+        let watch = spawn_json::<Vec<u32>, _>(&b"[1] [1, 2, 3] [2]"[..], options);
+        let events: Vec<_> = watch.iter().collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].as_ref().unwrap(), &[1]);
+        assert!(matches!(events[1], Err(Error::FrameTooLarge)));
+        assert_eq!(events[2].as_ref().unwrap(), &[2]);
+    }
+
+    #[test]
+    fn spawn_callback_events() {
+        let (tx, rx) = mpsc::channel();
+        let options = WatchOptions::default();
+        let callback = move |event: Result<u32, Error>| tx.send(event.unwrap()).unwrap();
+        spawn_callback("test", &b"1 2"[..], Interrupt::default(), options, Arc::new(Json), callback)
+            .unwrap();
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn spawn_callback_stop() {
+        let (tx, rx) = mpsc::channel();
+        let options = WatchOptions::default();
+        let reader = io::BufReader::new(IterReader(b"1 ".iter().cloned().cycle().map(Ok)));
+        let callback = move |event: Result<u32, Error>| tx.send(event.is_ok()).unwrap();
+        let handle = spawn_callback("test", reader, Interrupt::default(), options,
+                                    Arc::new(Json), callback)
+            .unwrap();
+        handle.stop();
+        // The callback is dropped once the worker stops, ending the iteration.
+        assert!(rx.iter().all(|ok| ok));
+    }
+
+    #[test]
+    fn spawn_max_json_depth() {
+        let options = WatchOptions {
+            max_json_depth: Some(2),
+            ..Default::default()
+        };
+        let watch = spawn_json::<Value, _>(&b"1 [[]] [[[]]] {\"a\": [1]}"[..], options);
+        let events: Vec<_> = watch.iter().map(|event| event.is_ok()).collect();
+        assert_eq!(events, vec![true, true, false, true]);
+    }
+
+    #[test]
+    fn check_depth_unlimited() {
+        let frame: Value = serde_json::from_str("[[[[[]]]]]").unwrap();
+        assert!(check_depth(frame.clone(), None).is_ok());
+        assert!(check_depth(frame, Some(5)).is_ok());
+    }
+
+    #[test]
+    fn check_json_depth_strings() {
+        assert!(check_json_depth(br#"[["[[", "\"[["], {"]]": 1}]"#, Some(2)).is_ok());
+        assert!(check_json_depth(br#"[[["]]"]]]"#, Some(2)).is_err());
+    }
+
+    #[test]
+    fn spawn_types() {
+        let stream = br#"{"type": "ADDED", "object": {}} {"object": {}} {"type": "DELETED"}"#;
+        let watch = super::spawn_types("test",
+                                       &stream[..],
+                                       Interrupt::default(),
+                                       WatchOptions::default(),
+                                       None,
+                                       Arc::new(Json))
+            .unwrap();
+        let events: Vec<_> = watch.iter().collect();
+        assert_eq!(events[0].as_ref().unwrap(), "ADDED");
+        assert!(matches!(events[1], Err(Error::InvalidEvent(_, _))));
+        assert_eq!(events[2].as_ref().unwrap(), "DELETED");
+        assert_eq!(watch.stats().events, 3);
+        assert!(watch.is_closed());
+    }
+
+    /// Spawn a worker reading types of JSON events from `stream`.
+    fn spawn_types_json(stream: &'static [u8], options: WatchOptions) -> Watch<String> {
+        super::spawn_types("test", stream, Interrupt::default(), options, None, Arc::new(Json))
+            .unwrap()
+    }
+
+    #[test]
+    fn spawn_types_dedup() {
+        let stream = br#"
+            {"type": "ADDED", "object": {"metadata": {"uid": "a", "resourceVersion": "1"}}}
+            {"type": "ADDED", "object": {"metadata": {"uid": "a", "resourceVersion": "1"}}}
+            {"type": "DELETED", "object": {"metadata": {"uid": "a", "resourceVersion": "2"}}}
+        "#;
+        let options = WatchOptions {
+            dedup: true,
+            ..Default::default()
+        };
+        let watch = spawn_types_json(stream, options);
+        let types: Vec<_> = watch.iter().map(Result::unwrap).collect();
+        assert_eq!(types, vec!["ADDED", "DELETED"]);
+        assert_eq!(watch.resource_version(), Some("2".to_owned()));
+    }
+
+    #[test]
+    fn spawn_types_expired() {
+        let stream = br#"{"type": "ADDED"} {"type": "ERROR", "object": {"code": 410}}
+                         {"type": "ADDED"}"#;
+        let watch = spawn_types_json(stream, WatchOptions::default());
+        let events: Vec<_> = watch.iter().collect();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1], Err(Error::Expired)));
+        assert!(!watch.is_closed());
+    }
+
+    #[test]
+    fn spawn_types_not_running() {
+        let options = WatchOptions {
+            running: Some(Arc::new(AtomicBool::new(false))),
+            ..Default::default()
+        };
+        let watch = spawn_types_json(br#"{"type": "ADDED"}"#, options);
+        assert_eq!(watch.iter().count(), 0);
+        assert_eq!(watch.status(), Some(WatchStatus::Cancelled));
+    }
+
+    #[test]
+    fn spawn_empty_stream() {
+        let watch = spawn_json::<u32, _>(&b""[..], WatchOptions::default());
+        let events: Vec<_> = watch.iter().collect();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], Err(Error::EmptyStream)));
+        assert!(!watch.is_closed());
+    }
+
+    #[test]
+    fn spawn_quiet_stream_timed_out() {
+        // The server ends quiet watches after its own timeout also when none was requested.
+        let quiet = ::std::iter::from_fn(|| {
+            thread::sleep(EMPTY_STREAM_WINDOW + Duration::from_millis(100));
+            None
+        });
+        let watch = spawn_json::<u32, _>(io::BufReader::new(IterReader(quiet)),
+                                         WatchOptions::default());
+        assert!(watch.iter().next().is_none());
+        assert!(watch.is_closed());
+    }
+
+    #[test]
+    fn spawn_empty_stream_timed_out() {
+        let options = WatchOptions { timeout_seconds: Some(1), ..Default::default() };
+        let watch = spawn_json::<u32, _>(&b""[..], options);
+        assert!(watch.iter().next().is_none());
+        assert!(watch.is_closed());
+    }
+
+    #[test]
+    fn trigger_after() {
+        let interrupt = Interrupt::default();
+        interrupt.trigger_after(Duration::from_millis(10)).unwrap();
+        assert!(!interrupt.is_triggered());
+        thread::sleep(Duration::from_millis(200));
+        assert!(interrupt.is_triggered());
+    }
+
+    #[test]
+    fn watch_reader() {
+        let input = &br#"{"type": "ADDED", "object": {"metadata": {"resourceVersion": "7"}}}
+                         {"type": "ERROR", "object": {"code": 410}}
+                         {"type": "ADDED", "object": {}}"#[..];
+        let mut reader = WatchReader::<Value>::new(input,
+                                                   Interrupt::default(),
+                                                   WatchOptions::default(),
+                                                   Arc::new(Json));
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(reader.next(), Some(Err(Error::Expired))));
+        assert!(reader.next().is_none());
+        assert!(!reader.is_closed());
+        assert_eq!(reader.resource_version(), Some("7".to_owned()));
+    }
+
+    #[test]
+    fn watch_reader_empty_stream() {
+        let mut reader = WatchReader::<Value>::new(&b""[..],
+                                                   Interrupt::default(),
+                                                   WatchOptions::default(),
+                                                   Arc::new(Json));
+        assert!(matches!(reader.next(), Some(Err(Error::EmptyStream))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn watch_reader_interrupted() {
+        let interrupt = Interrupt::default();
+        let mut reader = WatchReader::<Value>::new(&b"{}{}"[..],
+                                                   interrupt.clone(),
+                                                   WatchOptions::default(),
+                                                   Arc::new(Json));
+        assert!(reader.next().unwrap().is_ok());
+        interrupt.trigger();
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn snippet() {
+        assert_eq!(super::snippet(b"{\"x\": 1}"), "{\"x\": 1}");
+        let snippet = super::snippet(&[b'a'; SNIPPET_BYTES + 1]);
+        assert_eq!(snippet.len(), SNIPPET_BYTES + 3);
+        assert!(snippet.ends_with("a..."));
+    }
+
+    #[test]
+    fn spawn_shut_down() {
+        let running = Arc::new(AtomicBool::new(true));
+        let options = WatchOptions {
+            running: Some(running.clone()),
+            ..Default::default()
+        };
+        let watch = spawn_json::<u32, _>(io::empty(), options);
+        watch.shut_down();
+        assert!(!running.load(Ordering::SeqCst));
+        assert_eq!(watch.iter().count(), 0);
+    }
+
+    #[test]
+    fn forward_not_running() {
+        let (tx, rx) = channel::<u32>(None);
+        let interrupt = Interrupt::default();
+        let progress = Progress::default();
+        let options = WatchOptions {
+            running: Some(Arc::new(AtomicBool::new(false))),
+            ..Default::default()
+        };
+        let end = forward(&b"1 2 3"[..], &tx, &interrupt, &options, &Json, &progress);
+        assert_eq!(end, End::Stopped);
+        assert!(interrupt.is_triggered());
+        drop(tx);
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn spawn_last_event_at() {
+        let started = Instant::now();
+        let watch = spawn_json::<u32, _>(&b"1 2"[..], Default::default());
+        assert_eq!(watch.iter().count(), 2);
+        assert!(watch.last_event_at().is_some_and(|at| at >= started));
+        let watch = spawn_json::<u32, _>(&b"garbage"[..], Default::default());
+        assert_eq!(watch.iter().count(), 1);
+        assert_eq!(watch.last_event_at(), None);
+    }
+
+    #[test]
+    fn spawn_dedup() {
+        let stream = br#"
+            {"type": "ADDED", "object": {"metadata": {"uid": "a", "resourceVersion": "1"}}}
+            {"type": "ADDED", "object": {"metadata": {"uid": "a", "resourceVersion": "1"}}}
+            {"type": "ADDED", "object": {"metadata": {"uid": "b", "resourceVersion": "2"}}}
+            {"type": "MODIFIED", "object": {"metadata": {"uid": "a", "resourceVersion": "3"}}}
+            {"type": "DELETED", "object": {"metadata": {"uid": "a", "resourceVersion": "4"}}}
+            {"type": "DELETED", "object": {"metadata": {"uid": "a", "resourceVersion": "4"}}}
+        "#;
+        let options = WatchOptions {
+            dedup: true,
+            ..Default::default()
+        };
+        let watch = spawn_json::<Value, _>(&stream[..], options);
+        let versions: Vec<_> = watch.iter()
+            .map(|event| event.unwrap()["object"]["metadata"]["resourceVersion"].clone())
+            .collect();
+        assert_eq!(versions, vec!["1", "2", "3", "4", "4"]);
+        assert!(watch.progress.seen.lock().unwrap().contains_key("b"));
+        assert!(!watch.progress.seen.lock().unwrap().contains_key("a"));
+    }
+
+    #[test]
+    fn spawn_worker_name() {
+        let (tx, rx) = mpsc::channel();
+        spawn_worker("api/v1/pods", move || {
+            tx.send(thread::current().name().map(str::to_owned)).unwrap();
+        }).unwrap();
+        assert_eq!(rx.recv().unwrap(), Some("kubewatch api/v1/pods".to_owned()));
+    }
+
+    #[test]
+    fn shared() {
+        let watch = spawn_json::<u32, _>(&b"1 2 3 4"[..], Default::default()).shared();
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let events = watch.clone();
+                thread::spawn(move || events.map(Result::unwrap).collect::<Vec<_>>())
+            })
+            .collect();
+        let mut events: Vec<_> = workers.into_iter().flat_map(|w| w.join().unwrap()).collect();
+        events.sort();
+        assert_eq!(events, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn spawn_failed() {
+        let reader = io::BufReader::new(IterReader(Some(Err(io::ErrorKind::Other.into()))
+            .into_iter()));
+        let watch = spawn_json::<u32, _>(reader, Default::default());
+        assert!(matches!(watch.recv(), Ok(Err(Error::StreamIo(_)))));
+        assert!(watch.recv().is_err());
+        assert!(!watch.is_closed());
+        assert!(matches!(watch.status(), Some(WatchStatus::Errored(_))));
+    }
+
+    #[test]
+    fn merge_closed() {
+        let first = spawn_json::<u32, _>(&b"1"[..], Default::default());
+        let second = spawn_json::<u32, _>(&b"2"[..], Default::default());
+        let watches = vec![("first".to_owned(), first), ("second".to_owned(), second)];
+        let watch = merge(watches).unwrap();
+        assert_eq!(watch.iter().count(), 2);
+        assert!(watch.is_closed());
+    }
+
+    #[test]
+    fn expired() {
+        let expired = |frame: &str| FrameHeader::from_json(frame.as_bytes()).unwrap().is_expired();
+        assert!(expired(r#"{"type": "ERROR", "object": {"reason": "Expired", "code": 410}}"#));
+        assert!(!expired(r#"{"type": "ERROR", "object": {"code": 500}}"#));
+        assert!(!expired(r#"{"type": "ADDED", "object": {"code": 410}}"#));
+    }
+
+    #[test]
+    fn frames_incomplete() {
+        assert_eq!(frames(r#"{"a": 1}{"b": "#), vec![r#"{"a": 1}"#, r#"{"b": "#]);
+    }
+}